@@ -1,18 +1,19 @@
 use aid_distribution_with_assessments::MAX_ENTITLEMENT;
+use aid_distribution_with_assessments::MIN_ENTITLEMENT;
 use aid_distribution_with_assessments::NUM_RECIPIENTS;
 use aid_distribution_with_assessments::NUM_SHOW_UP;
 use aid_distribution_with_assessments::TAG_BYTELEN;
+use aid_distribution_with_assessments::auditor::Auditor;
+use aid_distribution_with_assessments::tags::{TagIssuer, derive_tags};
 use ark_ec::pairing::*;
 use ark_ff::PrimeField;
-use criterion::{Criterion, criterion_group, criterion_main};
-use rand::Rng;
+use criterion::{Criterion, black_box, criterion_group, criterion_main};
 use rand::thread_rng;
-use std::collections::HashSet;
-use tink_core::keyset;
 
 fn recipient<F: PrimeField>(
     b: u64,
     id: u16,
+    entitlement: usize,
     secret_tags: &Vec<[u8; TAG_BYTELEN]>,
     pk_enc_helper: &tink_core::keyset::Handle,
     pk_enc_auditor: &tink_core::keyset::Handle,
@@ -32,16 +33,18 @@ fn recipient<F: PrimeField>(
 
     let mut ctxts_1fe_pkehelper: Vec<Vec<u8>> = Vec::new();
 
-    // Encrypt 1FE ciphertext and secret_tag_{i,p,1} under auditor's public key
+    // Encrypt 1FE ciphertext, entitlement and secret_tag_{i,p,1} under auditor's public key
     let enc_auditor = tink_hybrid::new_encrypt(pk_enc_auditor).unwrap();
     let mut bytes_auditor = Vec::new();
     bytes_auditor.extend_from_slice(&secret_tags[0]); // secret_tag{i,p,k}
+    bytes_auditor.extend_from_slice(&(entitlement as u64).to_be_bytes()); // entitlement
     bytes_auditor.extend_from_slice(&ctxt_1fe); // 1FE ciphertext
     ctxts_1fe_pkehelper.push(ctxt_1fe);
     let ct_1fe_pkeauditor = enc_auditor.encrypt(&bytes_auditor, b"").unwrap();
 
     // 1FE.Encrypt dummy symbol 0 for MAX_ENTITLEMENT-1 times
-    // Encrypt all dummy 1FE ciphertexts and secret_tag{i,p,k} under auditor's public key
+    // Encrypt all dummy 1FE ciphertexts, entitlement and secret_tag{i,p,k} under auditor's public
+    // key
     let mut ctxts_auditor = Vec::new();
     ctxts_auditor.push(ct_1fe_pkeauditor);
     for k in 1..MAX_ENTITLEMENT {
@@ -49,6 +52,7 @@ fn recipient<F: PrimeField>(
 
         let mut bytes_auditor = Vec::new();
         bytes_auditor.extend_from_slice(&secret_tags[k]); // secret_tag{i,p,k}
+        bytes_auditor.extend_from_slice(&(entitlement as u64).to_be_bytes()); // entitlement
         bytes_auditor.extend_from_slice(&ctxt_1fe); // 1FE ciphertext
         ctxts_1fe_pkehelper.push(ctxt_1fe);
         let ct_1fe_pkeauditor = enc_auditor.encrypt(&bytes_auditor, b"").unwrap();
@@ -59,43 +63,12 @@ fn recipient<F: PrimeField>(
     (ctxts_1fe_pkehelper, ctxts_auditor)
 }
 
-fn bench_auditor(
-    ctxts_pke_auditor: &Vec<Vec<Vec<u8>>>,
-    valid_set: &HashSet<[u8; TAG_BYTELEN]>,
-    sk_enc_auditor: &keyset::Handle,
-) -> Vec<Vec<u8>> {
-    // Decrypt outer ciphertexts to secret tag and 1FE ciphertexts
-    let dec_auditor = tink_hybrid::new_decrypt(sk_enc_auditor).unwrap();
-    let mut secret_tags: Vec<Vec<[u8; TAG_BYTELEN]>> = Vec::new();
-    let mut ctxts_1fe = Vec::new();
-    for ctxts_recipient in ctxts_pke_auditor.iter() {
-        let mut recipient_secret_tags = Vec::new();
-        for ctxt in ctxts_recipient.iter() {
-            let pt = dec_auditor.decrypt(ctxt, b"").unwrap();
-            let secret_tag = pt[0..TAG_BYTELEN].try_into().unwrap();
-            recipient_secret_tags.push(secret_tag);
-
-            let ctxt_1fe = pt[TAG_BYTELEN..].to_vec();
-            ctxts_1fe.push(ctxt_1fe);
-        }
-        secret_tags.push(recipient_secret_tags);
-    }
-
-    // Check that all secret tags are distinct, are part of valid_set, and that there are claimed_entitlement many secret tags
-    let mut seen_tags = HashSet::new();
-    for recipient_tags in secret_tags.iter() {
-        for tag in recipient_tags.iter() {
-            if !valid_set.contains(tag) {
-                panic!("Invalid secret tag");
-            }
-            if seen_tags.contains(tag) {
-                panic!("Duplicate secret tag");
-            }
-            seen_tags.insert(*tag);
-        }
-    }
-
-    ctxts_1fe
+fn bench_auditor(auditor: &Auditor, id: u16, ctxts_pke_auditor: &[Vec<Vec<u8>>]) -> Vec<u8> {
+    // Decrypt outer ciphertexts, check secret tags against the registry and sign the 1FE
+    // ciphertexts of accepted submissions
+    let (report, approval) = auditor.audit(id, ctxts_pke_auditor).unwrap();
+    assert!(report.accepted.len() == ctxts_pke_auditor.len());
+    approval.signature
 }
 
 fn hbc_2pc_2_auditor(c: &mut Criterion) {
@@ -120,30 +93,46 @@ fn hbc_2pc_2_auditor(c: &mut Criterion) {
     .unwrap();
     let pk_enc_auditor = sk_enc_auditor.public().unwrap();
 
-    // Generate secret tags for recipients
-    let mut valid_set: HashSet<[u8; TAG_BYTELEN]> = HashSet::new();
-    let mut tags: Vec<Vec<[u8; TAG_BYTELEN]>> = Vec::new();
-    for i in 0..(NUM_RECIPIENTS) {
-        tags.push(Vec::new());
-        for _j in 0..(MAX_ENTITLEMENT) {
-            let mut tag = [0u8; TAG_BYTELEN];
-            rand::thread_rng().fill(&mut tag);
-            valid_set.insert(tag);
-            tags[i].push(tag);
-        }
-    }
+    // SIG.KeyGen for Auditor
+    tink_signature::init();
+    let sk_sig_auditor =
+        tink_core::keyset::Handle::new(&tink_signature::ecdsa_p256_key_template()).unwrap();
+
+    // Issue tag secrets with entitlements spread over [MIN_ENTITLEMENT, MAX_ENTITLEMENT], and
+    // derive the secret tags of this period from them
+    let entitlement = |i: usize| MIN_ENTITLEMENT + i % (MAX_ENTITLEMENT - MIN_ENTITLEMENT + 1);
+    let mut issuer = TagIssuer::new();
+    let tags = (0..NUM_RECIPIENTS)
+        .map(|i| {
+            let secret = issuer.register(i, entitlement(i)).unwrap();
+            derive_tags(&secret, id, MAX_ENTITLEMENT)
+        })
+        .collect::<Vec<_>>();
+    let valid_set = issuer.registry(id).unwrap();
 
     // Recipients encrypt
     let ctxts = (0..NUM_SHOW_UP)
-        .map(|i| recipient::<F>(1, id, &tags[i], &pk_enc_helper, &pk_enc_auditor))
+        .map(|i| {
+            recipient::<F>(
+                1,
+                id,
+                entitlement(i),
+                &tags[i],
+                &pk_enc_helper,
+                &pk_enc_auditor,
+            )
+        })
         .collect::<Vec<_>>();
     let ctxts_auditor = ctxts
         .iter()
         .map(|(_, ct_vec)| ct_vec.clone())
         .collect::<Vec<_>>();
 
+    // Auditor processes
+    let auditor = Auditor::new(sk_enc_auditor, sk_sig_auditor, valid_set);
+
     c.bench_function("hbc_2pc_2_auditor", |b| {
-        b.iter(|| bench_auditor(&ctxts_auditor, &valid_set, &sk_enc_auditor))
+        b.iter(|| bench_auditor(&auditor, id, black_box(&ctxts_auditor)))
     });
 }
 
@@ -0,0 +1,110 @@
+use aid_distribution_with_assessments::DECRYPTION_THRESHOLD;
+use aid_distribution_with_assessments::MAX_ENTITLEMENT;
+use aid_distribution_with_assessments::NUM_RECIPIENTS;
+use aid_distribution_with_assessments::thbgn::*;
+use ark_ec::pairing::Pairing;
+use ark_ec::pairing::PairingOutput;
+use ark_std::UniformRand;
+use criterion::{Criterion, black_box, criterion_group, criterion_main};
+use secret_sharing_and_dkg::common::ShareId;
+use secret_sharing_and_dkg::common::lagrange_basis_at_0_for_all;
+
+type P = ark_bls12_381::Bls12_381;
+type F = <P as Pairing>::ScalarField;
+
+const NUM_CTXTS: usize = 100 * MAX_ENTITLEMENT;
+
+/// Lagrange interpolation in the exponent with one scalar multiplication per share
+fn reconstruct_naive<G: ark_ec::Group>(shares: &[(ShareId, G)]) -> G {
+    let share_ids = shares.iter().map(|s| s.0).collect::<Vec<_>>();
+    let basis = lagrange_basis_at_0_for_all::<G::ScalarField>(share_ids).unwrap();
+    basis
+        .into_iter()
+        .zip(shares.iter())
+        .map(|(b, s)| s.1 * b)
+        .sum::<G>()
+}
+
+fn thbgn_encrypt(c: &mut Criterion) {
+    let pp = paramgen::<P>();
+    let (_sk, pk) = keygen::<P>(pp);
+    let mut rng = rand::thread_rng();
+    let msgs = (0..NUM_CTXTS)
+        .map(|_| F::rand(&mut rng))
+        .collect::<Vec<_>>();
+    let tables = encryption_tables::<P>(pp, pk, NUM_CTXTS);
+
+    c.bench_function("thbgn_encrypt", |b| {
+        b.iter(|| {
+            msgs.iter()
+                .map(|msg| encrypt::<P>(pp, pk, *msg))
+                .collect::<Vec<_>>()
+        })
+    });
+
+    c.bench_function("thbgn_encrypt_batch", |b| {
+        b.iter(|| encrypt_batch::<P>(&tables, black_box(&msgs)))
+    });
+}
+
+fn thbgn_inner_product(c: &mut Criterion) {
+    let pp = paramgen::<P>();
+    let (_sk, pk) = keygen::<P>(pp);
+    let cts_a = (0..NUM_CTXTS)
+        .map(|_| encrypt::<P>(pp, pk, F::from(1u64)))
+        .collect::<Vec<_>>();
+    let cts_b = (0..NUM_CTXTS)
+        .map(|_| encrypt::<P>(pp, pk, F::from(2u64)))
+        .collect::<Vec<_>>();
+
+    c.bench_function("thbgn_mul_add", |b| {
+        b.iter(|| {
+            cts_a
+                .iter()
+                .zip(cts_b.iter())
+                .map(|(ct_a, ct_b)| mul::<P>(pp, *ct_a, *ct_b))
+                .reduce(add_t::<P>)
+        })
+    });
+
+    c.bench_function("thbgn_inner_product", |b| {
+        b.iter(|| inner_product::<P>(pp, black_box(&cts_a), black_box(&cts_b)).unwrap())
+    });
+}
+
+fn thbgn_reconstruct(c: &mut Criterion) {
+    let pp = paramgen::<P>();
+    let (sk, _pk) = keygen::<P>(pp);
+    let shares = share_sk::<P>(sk, DECRYPTION_THRESHOLD, NUM_RECIPIENTS).unwrap();
+    let gt = P::pairing(pp.0, pp.1);
+    let shares_gt = shares
+        .iter()
+        .take(DECRYPTION_THRESHOLD)
+        .map(|(id, s1, _)| (*id, gt * s1))
+        .collect::<Vec<(ShareId, PairingOutput<P>)>>();
+    let shares_g1 = shares
+        .iter()
+        .take(DECRYPTION_THRESHOLD)
+        .map(|(id, s1, _)| (*id, pp.0 * s1))
+        .collect::<Vec<_>>();
+
+    c.bench_function("thbgn_reconstruct_gt", |b| {
+        b.iter(|| reconstruct_naive(black_box(&shares_gt)))
+    });
+    c.bench_function("thbgn_reconstruct_gt_msm", |b| {
+        b.iter(|| reconstruct_secret_in_exp(black_box(&shares_gt)).unwrap())
+    });
+    c.bench_function("thbgn_reconstruct_g1", |b| {
+        b.iter(|| reconstruct_naive(black_box(&shares_g1)))
+    });
+    c.bench_function("thbgn_reconstruct_g1_msm", |b| {
+        b.iter(|| reconstruct_secret_in_exp(black_box(&shares_g1)).unwrap())
+    });
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(10);
+    targets = thbgn_encrypt, thbgn_inner_product, thbgn_reconstruct
+}
+criterion_main!(benches);
@@ -0,0 +1,486 @@
+//! Auditor role of the thHE protocols: open the outer ciphertexts of recipients, check their secret
+//! tags and claimed entitlement against a registry, and approve the helper ciphertexts of
+//! well-formed submissions.
+use std::collections::{HashMap, HashSet};
+
+use tink_core::keyset;
+
+use crate::helper::auditor_message;
+use crate::protocol::Audit;
+use crate::tags::TagError;
+use crate::{MAX_ENTITLEMENT, TAG_BYTELEN};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AuditorError {
+    /// A Tink primitive could not be built from a keyset
+    Keyset,
+    /// Signing the approval failed
+    Signing,
+    /// (registry, audited) period
+    WrongPeriod(u16, u16),
+}
+
+impl std::fmt::Display for AuditorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuditorError::Keyset => write!(f, "invalid keyset"),
+            AuditorError::Signing => write!(f, "signing failed"),
+            AuditorError::WrongPeriod(expected, period) => {
+                write!(f, "registry for period {}, audited {}", expected, period)
+            }
+        }
+    }
+}
+
+impl std::error::Error for AuditorError {}
+
+/// Secret tags issued to recipients for a period, with the recipient each tag belongs to and the
+/// entitlement of each recipient
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TagRegistry {
+    period: u16,
+    owners: HashMap<[u8; TAG_BYTELEN], usize>,
+    entitlements: HashMap<usize, usize>,
+}
+
+impl TagRegistry {
+    pub fn new(period: u16) -> Self {
+        TagRegistry {
+            period,
+            owners: HashMap::new(),
+            entitlements: HashMap::new(),
+        }
+    }
+
+    pub fn period(&self) -> u16 {
+        self.period
+    }
+
+    /// Register the tags of `recipient`, one per slot of a submission, and their `entitlement`.
+    /// Nothing is registered if the recipient or one of the tags already is.
+    pub fn register(
+        &mut self,
+        recipient: usize,
+        entitlement: usize,
+        tags: impl IntoIterator<Item = [u8; TAG_BYTELEN]>,
+    ) -> Result<(), TagError> {
+        if self.entitlements.contains_key(&recipient) {
+            return Err(TagError::AlreadyRegistered(recipient));
+        }
+        let mut owners = HashMap::new();
+        for tag in tags {
+            if self.owners.contains_key(&tag) || owners.insert(tag, recipient).is_some() {
+                return Err(TagError::DuplicateTag(tag));
+            }
+        }
+        self.entitlements.insert(recipient, entitlement);
+        self.owners.extend(owners);
+        Ok(())
+    }
+
+    pub fn contains(&self, tag: &[u8; TAG_BYTELEN]) -> bool {
+        self.owners.contains_key(tag)
+    }
+
+    /// Recipient a tag was issued to
+    pub fn owner(&self, tag: &[u8; TAG_BYTELEN]) -> Option<usize> {
+        self.owners.get(tag).copied()
+    }
+
+    pub fn entitlement(&self, recipient: usize) -> Option<usize> {
+        self.entitlements.get(&recipient).copied()
+    }
+
+    /// Number of registered tags
+    pub fn len(&self) -> usize {
+        self.owners.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.owners.is_empty()
+    }
+}
+
+/// Outcome of auditing the submissions of a period. Recipients are identified by their index in
+/// the audited list.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AuditReport {
+    /// Recipients whose helper ciphertexts were approved
+    pub accepted: Vec<usize>,
+    /// Number of slots each recipient submitted
+    pub counts: Vec<usize>,
+    /// Recipients with an outer ciphertext that does not decrypt to a tag and a helper ciphertext
+    pub undecryptable: Vec<usize>,
+    /// Tags not in the registry, with the recipient who sent them
+    pub invalid_tags: Vec<(usize, [u8; TAG_BYTELEN])>,
+    /// Tags already used by an accepted submission of the period or twice in the same submission,
+    /// with the recipient who reused them
+    pub duplicate_tags: Vec<(usize, [u8; TAG_BYTELEN])>,
+    /// Recipients who did not send one slot for each of MAX_ENTITLEMENT tags
+    pub missing_tags: Vec<usize>,
+    /// Recipients whose tags are not all issued to one recipient, or who claim another
+    /// entitlement than the one registered for them
+    pub wrong_entitlement: Vec<usize>,
+}
+
+/// Helper ciphertexts of the accepted submissions of a period, grouped by recipient, with the
+/// audit of each recipient and the auditor's signature on `auditor_message`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Approval {
+    pub period: u16,
+    pub helper_ctxts: Vec<Vec<Vec<u8>>>,
+    pub audits: Vec<Audit>,
+    pub signature: Vec<u8>,
+}
+
+pub struct Auditor {
+    sk_enc: keyset::Handle,
+    sk_sig: keyset::Handle,
+    registry: TagRegistry,
+}
+
+impl Auditor {
+    pub fn new(sk_enc: keyset::Handle, sk_sig: keyset::Handle, registry: TagRegistry) -> Self {
+        tink_hybrid::init();
+        tink_signature::init();
+        Auditor {
+            sk_enc,
+            sk_sig,
+            registry,
+        }
+    }
+
+    pub fn registry(&self) -> &TagRegistry {
+        &self.registry
+    }
+
+    pub fn registry_mut(&mut self) -> &mut TagRegistry {
+        &mut self.registry
+    }
+
+    /// Audit the auditor ciphertexts of `period`, grouped by recipient. A submission is accepted
+    /// only if all its ciphertexts decrypt and carry MAX_ENTITLEMENT distinct registered tags
+    /// unused by earlier submissions, all issued to a single recipient, and the entitlement
+    /// registered for them. Only accepted submissions are signed, with that entitlement, which the
+    /// helper then checks against the one proven in a malicious submission. The registry must be
+    /// the one of `period`.
+    pub fn audit(
+        &self,
+        period: u16,
+        ctxts: &[Vec<Vec<u8>>],
+    ) -> Result<(AuditReport, Approval), AuditorError> {
+        if self.registry.period() != period {
+            return Err(AuditorError::WrongPeriod(self.registry.period(), period));
+        }
+        let dec = tink_hybrid::new_decrypt(&self.sk_enc).map_err(|_| AuditorError::Keyset)?;
+        let mut report = AuditReport::default();
+        let mut seen_tags = HashSet::new();
+        let mut helper_ctxts = Vec::new();
+        let mut audits = Vec::new();
+        for (i, ctxts_recipient) in ctxts.iter().enumerate() {
+            report.counts.push(ctxts_recipient.len());
+            let mut valid = true;
+            let mut tags = HashSet::new();
+            let mut owners = HashSet::new();
+            let mut entitlements = HashSet::new();
+            let mut tags_recipient = Vec::new();
+            let mut helper_ctxts_recipient = Vec::new();
+            for ct in ctxts_recipient {
+                let pt = match dec.decrypt(ct, b"") {
+                    Ok(pt) if pt.len() > TAG_BYTELEN + 8 => pt,
+                    _ => {
+                        valid = false;
+                        report.undecryptable.push(i);
+                        continue;
+                    }
+                };
+                let tag: [u8; TAG_BYTELEN] = pt[..TAG_BYTELEN].try_into().unwrap();
+                entitlements.insert(u64::from_be_bytes(
+                    pt[TAG_BYTELEN..TAG_BYTELEN + 8].try_into().unwrap(),
+                ));
+                let fresh = !seen_tags.contains(&tag) && tags.insert(tag);
+                tags_recipient.push(tag);
+                match self.registry.owner(&tag) {
+                    None => {
+                        valid = false;
+                        report.invalid_tags.push((i, tag));
+                    }
+                    Some(_) if !fresh => {
+                        valid = false;
+                        report.duplicate_tags.push((i, tag));
+                    }
+                    Some(owner) => {
+                        owners.insert(owner);
+                    }
+                }
+                helper_ctxts_recipient.push(pt[TAG_BYTELEN + 8..].to_vec());
+            }
+            report.undecryptable.dedup();
+            let registered = match owners.iter().collect::<Vec<_>>()[..] {
+                [owner] => self.registry.entitlement(*owner),
+                _ => None,
+            };
+            let entitlement = match entitlements.into_iter().collect::<Vec<_>>()[..] {
+                [entitlement] if registered.is_some_and(|e| e as u64 == entitlement) => {
+                    Some(entitlement)
+                }
+                _ => None,
+            };
+            if valid && ctxts_recipient.len() != MAX_ENTITLEMENT {
+                valid = false;
+                report.missing_tags.push(i);
+            } else if valid && entitlement.is_none() {
+                valid = false;
+                report.wrong_entitlement.push(i);
+            }
+            // Only accepted submissions use up their tags
+            if valid {
+                seen_tags.extend(tags);
+                report.accepted.push(i);
+                helper_ctxts.push(helper_ctxts_recipient);
+                audits.push(Audit {
+                    tags: tags_recipient,
+                    entitlement: entitlement.unwrap(),
+                });
+            }
+        }
+
+        let signer = tink_signature::new_signer(&self.sk_sig).map_err(|_| AuditorError::Keyset)?;
+        let signature = signer
+            .sign(&auditor_message(period, &helper_ctxts, &audits))
+            .map_err(|_| AuditorError::Signing)?;
+        Ok((
+            report,
+            Approval {
+                period,
+                helper_ctxts,
+                audits,
+                signature,
+            },
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::helper::Helper;
+    use crate::protocol::{F1, Security};
+    use crate::recipient::Recipient;
+    use crate::tags::{TagIssuer, derive_tags};
+    use crate::test_util::keys;
+    use crate::{MIN_ENTITLEMENT, thelgamal};
+    use ark_ec::pairing::Pairing;
+
+    type P = ark_bls12_381::Bls12_381;
+    type G1 = <P as Pairing>::G1;
+    type F = <P as Pairing>::ScalarField;
+
+    /// MAX_ENTITLEMENT distinct tags for each i
+    fn tags(i: u8) -> Vec<[u8; TAG_BYTELEN]> {
+        (0..MAX_ENTITLEMENT as u8)
+            .map(|k| [16 * i + k; TAG_BYTELEN])
+            .collect()
+    }
+
+    fn state_path(name: &str) -> std::path::PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("auditor-{}-{}.state", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn test_audit() {
+        let pp = thelgamal::paramgen::<G1>();
+        let (sk, pk) = thelgamal::keygen::<G1>(pp);
+        let shares = thelgamal::share_sk::<G1>(sk, 2, 3).unwrap();
+        let (sk_enc_helper, sk_sig_helper) = keys();
+        let (sk_enc_auditor, sk_sig_auditor) = keys();
+        let mut registry = TagRegistry::new(1);
+        registry.register(0, 2, tags(0)).unwrap();
+        registry.register(1, MAX_ENTITLEMENT, tags(1)).unwrap();
+        registry.register(2, 3, tags(2)).unwrap();
+        registry.register(3, 1, tags(3)).unwrap();
+        registry.register(4, 1, tags(4)).unwrap();
+        registry.register(5, 2, tags(5)).unwrap();
+        // Duplicate registrations are refused and leave the registry unchanged
+        assert_eq!(
+            registry.register(0, 1, [[0xf0; TAG_BYTELEN]]),
+            Err(TagError::AlreadyRegistered(0))
+        );
+        assert_eq!(
+            registry.register(6, 2, [[0xf0; TAG_BYTELEN], tags(0)[1]]),
+            Err(TagError::DuplicateTag(tags(0)[1]))
+        );
+        assert_eq!(
+            registry.register(6, 2, [[0xf0; TAG_BYTELEN], [0xf0; TAG_BYTELEN]]),
+            Err(TagError::DuplicateTag([0xf0; TAG_BYTELEN]))
+        );
+        assert!(!registry.contains(&[0xf0; TAG_BYTELEN]));
+        assert_eq!(registry.entitlement(6), None);
+
+        let mut unregistered = tags(2);
+        unregistered[MAX_ENTITLEMENT - 1] = [0xff; TAG_BYTELEN];
+        let mut reused = tags(3);
+        reused[0] = tags(0)[0];
+        let submissions = [
+            (tags(0), 2),
+            // Recipients below and at MAX_ENTITLEMENT send as many slots
+            (tags(1), MAX_ENTITLEMENT),
+            // Unregistered tag
+            (unregistered, 3),
+            // Reuses a tag of the first recipient
+            (reused, 1),
+            // Claims another entitlement than the registered one
+            (tags(4), 3),
+            // Misses a slot
+            (tags(5), 2),
+            // Tags of a rejected submission are not used up
+            (tags(2), 3),
+        ];
+        let mut ctxts = submissions
+            .iter()
+            .enumerate()
+            .map(|(i, (tags, entitlement))| {
+                Recipient::<F1<G1>>::new(
+                    i,
+                    Security::HonestButCurious,
+                    pp,
+                    pk,
+                    shares[0],
+                    sk_enc_helper.public().unwrap(),
+                    sk_sig_helper.public().unwrap(),
+                )
+                .with_auditor(sk_enc_auditor.public().unwrap(), *entitlement, tags.clone())
+                .submit(1, true, 0)
+                .unwrap()
+                .auditor_ctxts
+            })
+            .collect::<Vec<_>>();
+        ctxts[5].pop();
+        ctxts.push(vec![b"garbage".to_vec()]);
+
+        let vk_sig_auditor = sk_sig_auditor.public().unwrap();
+        let auditor = Auditor::new(sk_enc_auditor, sk_sig_auditor, registry);
+        assert_eq!(auditor.registry().len(), 6 * MAX_ENTITLEMENT);
+        assert_eq!(auditor.registry().owner(&tags(2)[0]), Some(2));
+        // The registry of period 1 does not audit another period
+        assert_eq!(
+            auditor.audit(2, &ctxts).err(),
+            Some(AuditorError::WrongPeriod(1, 2))
+        );
+        let (report, approval) = auditor.audit(1, &ctxts).unwrap();
+        assert_eq!(report.accepted, vec![0, 1, 6]);
+        assert_eq!(report.counts, vec![5, 5, 5, 5, 5, 4, 5, 1]);
+        assert_eq!(report.undecryptable, vec![7]);
+        assert_eq!(report.invalid_tags, vec![(2, [0xff; TAG_BYTELEN])]);
+        assert_eq!(report.duplicate_tags, vec![(3, tags(0)[0])]);
+        assert_eq!(report.missing_tags, vec![5]);
+        assert_eq!(report.wrong_entitlement, vec![4]);
+        assert_eq!(approval.helper_ctxts.len(), 3);
+        assert_eq!(
+            approval.audits[0],
+            Audit {
+                tags: tags(0),
+                entitlement: 2
+            }
+        );
+
+        // The helper evaluates exactly the approved ciphertexts
+        let path = state_path("hbc");
+        let mut helper = Helper::open(
+            &path,
+            Security::HonestButCurious,
+            sk_enc_helper,
+            sk_sig_helper,
+        )
+        .unwrap()
+        .with_auditor(vk_sig_auditor);
+        let evaluation = helper
+            .process_f1::<G1>(
+                pp,
+                pk,
+                approval.period,
+                &approval.helper_ctxts,
+                Some((&approval.audits, &approval.signature)),
+            )
+            .unwrap();
+        assert!(evaluation.rejected.is_empty());
+        // Each accepted recipient sent the indicator in as many slots as their entitlement
+        assert_eq!(
+            thelgamal::decrypt::<G1>(pp, sk, evaluation.output.output, 16).unwrap(),
+            F::from((2 + MAX_ENTITLEMENT + 3) as u64)
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_audit_malicious() {
+        let pp = thelgamal::paramgen::<G1>();
+        let (sk, pk) = thelgamal::keygen::<G1>(pp);
+        let shares = thelgamal::share_sk::<G1>(sk, 2, 3).unwrap();
+        let (sk_enc_helper, sk_sig_helper) = keys();
+        let (sk_enc_auditor, sk_sig_auditor) = keys();
+        let mut issuer = TagIssuer::new();
+        let secrets = [MIN_ENTITLEMENT, MAX_ENTITLEMENT, 2]
+            .into_iter()
+            .enumerate()
+            .map(|(i, entitlement)| issuer.register(i, entitlement).unwrap())
+            .collect::<Vec<_>>();
+        // The last recipient claims more than their entitlement
+        let claimed = [MIN_ENTITLEMENT, MAX_ENTITLEMENT, 3];
+        let ctxts = shares
+            .iter()
+            .enumerate()
+            .map(|(i, share)| {
+                Recipient::<F1<G1>>::new(
+                    i,
+                    Security::Malicious,
+                    pp,
+                    pk,
+                    *share,
+                    sk_enc_helper.public().unwrap(),
+                    sk_sig_helper.public().unwrap(),
+                )
+                .with_auditor(
+                    sk_enc_auditor.public().unwrap(),
+                    claimed[i],
+                    derive_tags(&secrets[i], 4, MAX_ENTITLEMENT),
+                )
+                .submit(4, true, 0)
+                .unwrap()
+                .auditor_ctxts
+            })
+            .collect::<Vec<_>>();
+
+        let vk_sig_auditor = sk_sig_auditor.public().unwrap();
+        let auditor = Auditor::new(sk_enc_auditor, sk_sig_auditor, issuer.registry(4).unwrap());
+        let (report, approval) = auditor.audit(4, &ctxts).unwrap();
+        assert_eq!(report.accepted, vec![0, 1]);
+        assert_eq!(report.wrong_entitlement, vec![2]);
+        assert_eq!(approval.audits[0].entitlement, MIN_ENTITLEMENT as u64);
+
+        let path = state_path("mal");
+        let mut helper = Helper::open(&path, Security::Malicious, sk_enc_helper, sk_sig_helper)
+            .unwrap()
+            .with_auditor(vk_sig_auditor);
+        let evaluation = helper
+            .process_f1::<G1>(
+                pp,
+                pk,
+                approval.period,
+                &approval.helper_ctxts,
+                Some((&approval.audits, &approval.signature)),
+            )
+            .unwrap();
+        // The submission below MAX_ENTITLEMENT proves the audited entitlement and is counted once
+        // per unit of entitlement
+        assert!(evaluation.rejected.is_empty());
+        assert_eq!(
+            thelgamal::decrypt::<G1>(pp, sk, evaluation.output.output, 16).unwrap(),
+            F::from((MIN_ENTITLEMENT + MAX_ENTITLEMENT) as u64)
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,726 @@
+//! Joint-Feldman distributed key generation: every participant deals a random secret with
+//! Feldman VSS, and the key is the sum of the secrets of the dealers nobody complained about.
+//! Received shares are checked and summed by the `SharesAccumulator` of
+//! `secret_sharing_and_dkg::feldman_dvss_dkg`.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use ark_ec::pairing::Pairing;
+use ark_ec::{AffineRepr, CurveGroup};
+use ark_std::{UniformRand, Zero};
+use rand::thread_rng;
+use secret_sharing_and_dkg::common::{
+    Share, ShareId, SharesAccumulator, lagrange_basis_at_0_for_all,
+};
+use secret_sharing_and_dkg::error::SSError;
+
+use crate::thbgn;
+use crate::thelgamal;
+use crate::vss::{self, Commitments};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DkgError {
+    /// (threshold, total) does not describe a valid sharing
+    InvalidThreshold(usize, usize),
+    /// The id is 0 or larger than the number of participants
+    UnknownParticipant(ShareId),
+    /// A dealer sent more than one dealing, or is listed twice as qualified
+    DuplicateDealing(ShareId),
+    /// The share sent by this dealer does not match its commitments
+    InvalidShare(ShareId),
+    /// (threshold, number of qualified dealers)
+    NotEnoughQualified(usize, usize),
+}
+
+impl std::fmt::Display for DkgError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DkgError::InvalidThreshold(threshold, total) => {
+                write!(
+                    f,
+                    "invalid threshold {} for {} participants",
+                    threshold, total
+                )
+            }
+            DkgError::UnknownParticipant(id) => write!(f, "unknown participant {}", id),
+            DkgError::DuplicateDealing(id) => write!(f, "duplicate dealing from {}", id),
+            DkgError::InvalidShare(id) => write!(f, "invalid share from dealer {}", id),
+            DkgError::NotEnoughQualified(threshold, len) => {
+                write!(f, "{} qualified dealers, {} needed", len, threshold)
+            }
+        }
+    }
+}
+
+impl std::error::Error for DkgError {}
+
+/// Output of a dealer: commitments to broadcast, and one share to send privately to each
+/// participant
+#[derive(Clone, Debug)]
+pub struct Dealing<G: CurveGroup> {
+    pub dealer: ShareId,
+    pub commitments: Commitments<G>,
+    shares: Vec<(ShareId, G::ScalarField)>,
+}
+
+impl<G: CurveGroup> Dealing<G> {
+    /// The private share for participant `id`
+    pub fn share_for(&self, id: ShareId) -> Option<G::ScalarField> {
+        self.shares.iter().find(|(i, _)| *i == id).map(|(_, s)| *s)
+    }
+}
+
+/// Result of the DKG for one participant
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DkgOutput<G: CurveGroup> {
+    pub id: ShareId,
+    pub share: G::ScalarField,
+    pub public_key: G,
+    /// Commitments to the joint sharing polynomial, against which any share can be checked
+    pub commitments: Commitments<G>,
+}
+
+/// State of one participant in a DKG over `base`
+pub struct Participant<G: CurveGroup> {
+    id: ShareId,
+    threshold: usize,
+    /// Ids of all participants, including this one
+    ids: BTreeSet<ShareId>,
+    base: G,
+    /// Deal and accept only sharings of zero, to refresh an existing sharing
+    refresh: bool,
+    received: SharesAccumulator<G::Affine, Share<G::ScalarField>>,
+}
+
+impl<G: CurveGroup> Participant<G> {
+    /// Participant `id` among participants 1, ..., `total`
+    pub fn new(base: G, id: ShareId, threshold: usize, total: usize) -> Result<Self, DkgError> {
+        if total > ShareId::MAX as usize {
+            return Err(DkgError::InvalidThreshold(threshold, total));
+        }
+        Self::with_ids(
+            base,
+            id,
+            threshold,
+            &(1..=total as ShareId).collect::<Vec<_>>(),
+        )
+    }
+
+    /// Participant `id` among participants with the given non-zero, distinct `ids`
+    pub fn with_ids(
+        base: G,
+        id: ShareId,
+        threshold: usize,
+        ids: &[ShareId],
+    ) -> Result<Self, DkgError> {
+        let ids_set = ids.iter().copied().collect::<BTreeSet<_>>();
+        if threshold == 0 || threshold > ids.len() || ids_set.len() != ids.len() {
+            return Err(DkgError::InvalidThreshold(threshold, ids.len()));
+        }
+        if let Some(id) = ids.iter().find(|id| **id == 0) {
+            return Err(DkgError::UnknownParticipant(*id));
+        }
+        if !ids_set.contains(&id) {
+            return Err(DkgError::UnknownParticipant(id));
+        }
+        Ok(Self {
+            id,
+            threshold,
+            ids: ids_set,
+            base,
+            refresh: false,
+            received: SharesAccumulator::new(id, threshold as ShareId),
+        })
+    }
+
+    /// Participant `id` in a proactive refresh of a sharing held by `ids`
+    pub fn for_refresh(
+        base: G,
+        id: ShareId,
+        threshold: usize,
+        ids: &[ShareId],
+    ) -> Result<Self, DkgError> {
+        let mut participant = Self::with_ids(base, id, threshold, ids)?;
+        participant.refresh = true;
+        Ok(participant)
+    }
+
+    pub fn id(&self) -> ShareId {
+        self.id
+    }
+
+    /// Deal a fresh random secret to all participants, or zero when refreshing
+    pub fn deal(&self) -> Result<Dealing<G>, DkgError> {
+        let secret = if self.refresh {
+            G::ScalarField::zero()
+        } else {
+            G::ScalarField::rand(&mut thread_rng())
+        };
+        let ids = self.ids.iter().copied().collect::<Vec<_>>();
+        let (shares, commitments) = vss::deal_to(self.base, secret, self.threshold, &ids)
+            .map_err(|_| DkgError::InvalidThreshold(self.threshold, ids.len()))?;
+        Ok(Dealing {
+            dealer: self.id,
+            commitments,
+            shares,
+        })
+    }
+
+    /// Process the commitments broadcast by `dealer` and the share it sent us. An
+    /// `InvalidShare` error is a complaint against `dealer`, which is then left out of the
+    /// qualified set.
+    pub fn receive(
+        &mut self,
+        dealer: ShareId,
+        commitments: &Commitments<G>,
+        share: G::ScalarField,
+    ) -> Result<(), DkgError> {
+        if !self.ids.contains(&dealer) {
+            return Err(DkgError::UnknownParticipant(dealer));
+        }
+        if commitments.threshold() != self.threshold
+            || (self.refresh && !commitments.public_value().is_zero())
+        {
+            return Err(DkgError::InvalidShare(dealer));
+        }
+        let share = Share {
+            id: self.id,
+            threshold: self.threshold as ShareId,
+            share,
+        };
+        if dealer != self.id {
+            return self
+                .received
+                .add_received_share(
+                    dealer,
+                    share,
+                    commitments.coefficients(),
+                    &self.base.into_affine(),
+                )
+                .map_err(|e| match e {
+                    SSError::AlreadyProcessedFromSender(_) => DkgError::DuplicateDealing(dealer),
+                    _ => DkgError::InvalidShare(dealer),
+                });
+        }
+        // The accumulator trusts our own share, check it anyway
+        if self.received.shares.contains_key(&dealer) {
+            return Err(DkgError::DuplicateDealing(dealer));
+        }
+        if !commitments.verify(self.base, self.id, &share.share) {
+            return Err(DkgError::InvalidShare(dealer));
+        }
+        self.received
+            .add_self_share(share, commitments.coefficients());
+        Ok(())
+    }
+
+    /// Combine the dealings of the `qualified` dealers, i.e. those no participant complained
+    /// about. All honest participants must use the same qualified set, without repeated dealers.
+    pub fn finalize(&self, qualified: &[ShareId]) -> Result<DkgOutput<G>, DkgError> {
+        let mut seen = BTreeSet::new();
+        if let Some(dealer) = qualified.iter().find(|dealer| !seen.insert(**dealer)) {
+            return Err(DkgError::DuplicateDealing(*dealer));
+        }
+        let mut shares = BTreeMap::new();
+        let mut coeffs = BTreeMap::new();
+        let mut commitments = Vec::with_capacity(qualified.len());
+        for dealer in qualified {
+            let share = self
+                .received
+                .shares
+                .get(dealer)
+                .ok_or(DkgError::InvalidShare(*dealer))?;
+            let c = &self.received.coeff_comms[dealer];
+            shares.insert(*dealer, share.clone());
+            coeffs.insert(*dealer, c.clone());
+            commitments.push(Commitments::from(c));
+        }
+        if qualified.len() < self.threshold {
+            return Err(DkgError::NotEnoughQualified(
+                self.threshold,
+                qualified.len(),
+            ));
+        }
+        let (share, _, public_key) =
+            SharesAccumulator::<G::Affine, Share<G::ScalarField>>::gen_final_share_and_public_key(
+                self.id,
+                self.threshold as ShareId,
+                shares,
+                coeffs,
+                &self.base.into_affine(),
+            )
+            .map_err(|_| DkgError::NotEnoughQualified(self.threshold, qualified.len()))?;
+        Ok(DkgOutput {
+            id: self.id,
+            share: share.share,
+            public_key: public_key.into_group(),
+            commitments: Commitments::combine(&commitments),
+        })
+    }
+}
+
+/// Run the DKG among `total` simulated parties in-process, with all dealers honest.
+pub fn run<G: CurveGroup>(
+    base: G,
+    threshold: usize,
+    total: usize,
+) -> Result<Vec<DkgOutput<G>>, DkgError> {
+    let mut parties = (1..=total)
+        .map(|id| Participant::new(base, id as ShareId, threshold, total))
+        .collect::<Result<Vec<_>, _>>()?;
+    let dealings = parties
+        .iter()
+        .map(|p| p.deal())
+        .collect::<Result<Vec<_>, _>>()?;
+    run_with_dealings(&mut parties, &dealings)
+}
+
+/// Deliver `dealings` to `parties`, disqualify every dealer with a complaint against it, and
+/// finalize.
+fn run_with_dealings<G: CurveGroup>(
+    parties: &mut [Participant<G>],
+    dealings: &[Dealing<G>],
+) -> Result<Vec<DkgOutput<G>>, DkgError> {
+    let mut disqualified = BTreeSet::new();
+    for party in parties.iter_mut() {
+        for dealing in dealings {
+            let received = match dealing.share_for(party.id()) {
+                Some(share) => party.receive(dealing.dealer, &dealing.commitments, share),
+                None => Err(DkgError::InvalidShare(dealing.dealer)),
+            };
+            match received {
+                Ok(()) => {}
+                Err(DkgError::InvalidShare(dealer)) => {
+                    disqualified.insert(dealer);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+    let qualified = dealings
+        .iter()
+        .map(|d| d.dealer)
+        .filter(|d| !disqualified.contains(d))
+        .collect::<Vec<_>>();
+    parties.iter().map(|p| p.finalize(&qualified)).collect()
+}
+
+/// Generate a threshold BGN key with one DKG for s1 in base g1 and one for s2 in base g2. The
+/// joint commitments can be checked against the public key with `thbgn::verify_share`.
+pub fn dkg_thbgn<P: Pairing>(
+    pp: thbgn::PublicParameters<P>,
+    threshold: usize,
+    total: usize,
+) -> Result<
+    (
+        thbgn::PublicKey<P>,
+        thbgn::ShareCommitments<P>,
+        Vec<thbgn::SecretKeyShare<P>>,
+    ),
+    DkgError,
+> {
+    let (g1, g2) = pp;
+    let out1 = run(g1, threshold, total)?;
+    let out2 = run(g2, threshold, total)?;
+    let pk = (out1[0].public_key, out2[0].public_key);
+    let commitments = (out1[0].commitments.clone(), out2[0].commitments.clone());
+    let shares = out1
+        .into_iter()
+        .zip(out2)
+        .map(|(o1, o2)| (o1.id, o1.share, o2.share))
+        .collect();
+    Ok((pk, commitments, shares))
+}
+
+/// Generate a threshold ElGamal key, with commitments to the joint sharing polynomial.
+pub fn dkg_thelgamal<G: CurveGroup>(
+    pp: thelgamal::PublicParameters<G>,
+    threshold: usize,
+    total: usize,
+) -> Result<
+    (
+        thelgamal::PublicKey<G>,
+        Commitments<G>,
+        Vec<thelgamal::SecretKeyShare<G>>,
+    ),
+    DkgError,
+> {
+    let out = run(pp, threshold, total)?;
+    let pk = out[0].public_key;
+    let commitments = out[0].commitments.clone();
+    Ok((
+        pk,
+        commitments,
+        out.into_iter().map(|o| (o.id, o.share)).collect(),
+    ))
+}
+
+/// Proactively refresh the sharing `shares` of a secret committed to by `commitments`: every
+/// holder deals a sharing of zero to the others, and adds the zero-shares it receives from the
+/// qualified dealers to its share. The secret, hence the public key, is unchanged, but shares from
+/// before and after the refresh cannot be combined. Returns the new commitments and shares.
+pub fn refresh<G: CurveGroup>(
+    base: G,
+    commitments: &Commitments<G>,
+    shares: &[(ShareId, G::ScalarField)],
+) -> Result<(Commitments<G>, Vec<(ShareId, G::ScalarField)>), DkgError> {
+    let threshold = commitments.threshold();
+    let ids = shares.iter().map(|(id, _)| *id).collect::<Vec<_>>();
+    let mut parties = ids
+        .iter()
+        .map(|id| Participant::for_refresh(base, *id, threshold, &ids))
+        .collect::<Result<Vec<_>, _>>()?;
+    let dealings = parties
+        .iter()
+        .map(|p| p.deal())
+        .collect::<Result<Vec<_>, _>>()?;
+    let outs = run_with_dealings(&mut parties, &dealings)?;
+    let commitments = Commitments::combine([commitments, &outs[0].commitments]);
+    let shares = shares
+        .iter()
+        .zip(outs)
+        .map(|((id, share), out)| (*id, *share + out.share))
+        .collect();
+    Ok((commitments, shares))
+}
+
+/// Refresh both BGN key sharings, see `refresh`.
+pub fn refresh_thbgn<P: Pairing>(
+    pp: thbgn::PublicParameters<P>,
+    commitments: &thbgn::ShareCommitments<P>,
+    shares: &[thbgn::SecretKeyShare<P>],
+) -> Result<(thbgn::ShareCommitments<P>, Vec<thbgn::SecretKeyShare<P>>), DkgError> {
+    let (g1, g2) = pp;
+    let (commitments1, shares1) = refresh(
+        g1,
+        &commitments.0,
+        &shares
+            .iter()
+            .map(|(id, s1, _)| (*id, *s1))
+            .collect::<Vec<_>>(),
+    )?;
+    let (commitments2, shares2) = refresh(
+        g2,
+        &commitments.1,
+        &shares
+            .iter()
+            .map(|(id, _, s2)| (*id, *s2))
+            .collect::<Vec<_>>(),
+    )?;
+    let shares = shares1
+        .into_iter()
+        .zip(shares2)
+        .map(|((id, s1), (_, s2))| (id, s1, s2))
+        .collect();
+    Ok(((commitments1, commitments2), shares))
+}
+
+/// Move the sharing `old_shares` of a secret committed to by `old_commitments` to a sharing with
+/// threshold `new_threshold` held by `new_ids`, e.g. when recipients enroll or leave between
+/// periods. Every old holder deals a sharing of its own share to the new holders, and each new
+/// holder combines the dealings of `old_commitments.threshold()` qualified old holders with
+/// Lagrange coefficients. The secret, hence the public key, is unchanged. Returns the new
+/// commitments and shares, in the order of `new_ids`.
+pub fn reshare<G: CurveGroup>(
+    base: G,
+    old_commitments: &Commitments<G>,
+    old_shares: &[(ShareId, G::ScalarField)],
+    new_threshold: usize,
+    new_ids: &[ShareId],
+) -> Result<(Commitments<G>, Vec<(ShareId, G::ScalarField)>), DkgError> {
+    let dealings = old_shares
+        .iter()
+        .map(|(id, share)| {
+            let (shares, commitments) = vss::deal_to(base, *share, new_threshold, new_ids)
+                .map_err(|_| DkgError::InvalidThreshold(new_threshold, new_ids.len()))?;
+            Ok(Dealing {
+                dealer: *id,
+                commitments,
+                shares,
+            })
+        })
+        .collect::<Result<Vec<_>, DkgError>>()?;
+    reshare_with_dealings(base, old_commitments, &dealings, new_threshold, new_ids)
+}
+
+/// Deliver the resharing `dealings` to the new holders, disqualify every old holder with a
+/// complaint against it, and combine the dealings of the first qualified ones.
+fn reshare_with_dealings<G: CurveGroup>(
+    base: G,
+    old_commitments: &Commitments<G>,
+    dealings: &[Dealing<G>],
+    new_threshold: usize,
+    new_ids: &[ShareId],
+) -> Result<(Commitments<G>, Vec<(ShareId, G::ScalarField)>), DkgError> {
+    let old_threshold = old_commitments.threshold();
+    let mut qualified = BTreeSet::new();
+    for dealing in dealings {
+        // The dealt secret must be the dealer's old share
+        let valid = dealing.dealer != 0
+            && dealing.commitments.threshold() == new_threshold
+            && dealing.commitments.public_value() == old_commitments.eval(dealing.dealer)
+            && new_ids.iter().all(|id| {
+                dealing
+                    .share_for(*id)
+                    .is_some_and(|share| dealing.commitments.verify(base, *id, &share))
+            });
+        if valid && !qualified.insert(dealing.dealer) {
+            return Err(DkgError::DuplicateDealing(dealing.dealer));
+        }
+    }
+    if qualified.len() < old_threshold {
+        return Err(DkgError::NotEnoughQualified(old_threshold, qualified.len()));
+    }
+    let qualified = qualified
+        .into_iter()
+        .take(old_threshold)
+        .collect::<Vec<_>>();
+    let dealings = qualified
+        .iter()
+        .map(|id| dealings.iter().find(|d| d.dealer == *id).unwrap())
+        .collect::<Vec<_>>();
+    let basis = lagrange_basis_at_0_for_all::<G::ScalarField>(qualified.clone())
+        .map_err(|_| DkgError::InvalidThreshold(old_threshold, qualified.len()))?;
+
+    let commitments = basis
+        .iter()
+        .zip(dealings.iter())
+        .map(|(b, d)| d.commitments.scale(*b))
+        .collect::<Vec<_>>();
+    let shares = new_ids
+        .iter()
+        .map(|id| {
+            let share = basis
+                .iter()
+                .zip(dealings.iter())
+                .map(|(b, d)| *b * d.share_for(*id).unwrap())
+                .sum();
+            (*id, share)
+        })
+        .collect();
+    Ok((Commitments::combine(&commitments), shares))
+}
+
+/// Reshare both BGN key sharings, see `reshare`.
+pub fn reshare_thbgn<P: Pairing>(
+    pp: thbgn::PublicParameters<P>,
+    commitments: &thbgn::ShareCommitments<P>,
+    shares: &[thbgn::SecretKeyShare<P>],
+    new_threshold: usize,
+    new_ids: &[ShareId],
+) -> Result<(thbgn::ShareCommitments<P>, Vec<thbgn::SecretKeyShare<P>>), DkgError> {
+    let (g1, g2) = pp;
+    let (commitments1, shares1) = reshare(
+        g1,
+        &commitments.0,
+        &shares
+            .iter()
+            .map(|(id, s1, _)| (*id, *s1))
+            .collect::<Vec<_>>(),
+        new_threshold,
+        new_ids,
+    )?;
+    let (commitments2, shares2) = reshare(
+        g2,
+        &commitments.1,
+        &shares
+            .iter()
+            .map(|(id, _, s2)| (*id, *s2))
+            .collect::<Vec<_>>(),
+        new_threshold,
+        new_ids,
+    )?;
+    let shares = shares1
+        .into_iter()
+        .zip(shares2)
+        .map(|((id, s1), (_, s2))| (id, s1, s2))
+        .collect();
+    Ok(((commitments1, commitments2), shares))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    type P = ark_bls12_381::Bls12_381;
+    type G1 = <P as Pairing>::G1;
+    type F = <P as Pairing>::ScalarField;
+
+    #[test]
+    fn test_dkg_thbgn() {
+        let pp = thbgn::paramgen::<P>();
+        let (pk, commitments, sks) = dkg_thbgn::<P>(pp, 3, 5).unwrap();
+        assert!(
+            sks.iter()
+                .all(|sk| thbgn::verify_share::<P>(pp, pk, &commitments, *sk))
+        );
+
+        let msg = F::from(3u64);
+        let ct0 = thbgn::encrypt::<P>(pp, pk, msg);
+        let ct = thbgn::mul::<P>(pp, ct0, ct0);
+        let pdecs = sks
+            .iter()
+            .map(|sk| thbgn::partial_decrypt::<P>(pp, *sk, ct))
+            .collect::<Vec<_>>();
+        let inter = thbgn::intermediate_dec::<P>(pp, &pdecs[2..], 3).unwrap();
+        let pdecs2 = sks[2..]
+            .iter()
+            .map(|sk| thbgn::partial_decrypt2::<P>(pp, *sk, inter))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            thbgn::final_decrypt::<P>(pp, &pdecs2, 3, 1 << 4).unwrap(),
+            msg * msg
+        );
+    }
+
+    #[test]
+    fn test_dkg_thelgamal_cheating_dealer() {
+        let pp = thelgamal::paramgen::<G1>();
+        let mut parties = (1..=5)
+            .map(|id| Participant::new(pp, id, 3, 5).unwrap())
+            .collect::<Vec<_>>();
+        let mut dealings = parties
+            .iter()
+            .map(|p| p.deal().unwrap())
+            .collect::<Vec<_>>();
+        // Dealer 2 sends a bad share to participant 4
+        dealings[1].shares[3].1 += F::from(1u64);
+
+        let outs = run_with_dealings(&mut parties, &dealings).unwrap();
+        let qualified = Commitments::combine(
+            dealings
+                .iter()
+                .filter(|d| d.dealer != 2)
+                .map(|d| &d.commitments),
+        );
+        let pk = outs[0].public_key;
+        assert_eq!(pk, qualified.public_value());
+        for out in outs.iter() {
+            assert_eq!(out.public_key, pk);
+            assert!(out.commitments.verify(pp, out.id, &out.share));
+        }
+
+        let sks = outs.iter().map(|o| (o.id, o.share)).collect::<Vec<_>>();
+        let msg = F::from(5u64);
+        let ct = thelgamal::encrypt::<G1>(pp, pk, msg);
+        let pdecs = sks
+            .iter()
+            .map(|sk| thelgamal::partial_decrypt::<G1>(pp, *sk, ct))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            thelgamal::final_decrypt::<G1>(pp, ct, &pdecs[1..4], 3, 1 << 4).unwrap(),
+            msg
+        );
+
+        let mut party = Participant::new(pp, 1, 3, 5).unwrap();
+        assert_eq!(
+            party.receive(
+                2,
+                &dealings[1].commitments,
+                dealings[0].share_for(1).unwrap()
+            ),
+            Err(DkgError::InvalidShare(2))
+        );
+        assert_eq!(
+            party.finalize(&[1, 3]).unwrap_err(),
+            DkgError::InvalidShare(1)
+        );
+        // A repeated dealer would count twice towards the threshold and the joint commitments
+        assert_eq!(
+            parties[0].finalize(&[1, 3, 3]).unwrap_err(),
+            DkgError::DuplicateDealing(3)
+        );
+        assert_eq!(
+            parties[0].finalize(&[1, 3, 4, 1]).unwrap_err(),
+            DkgError::DuplicateDealing(1)
+        );
+    }
+
+    #[test]
+    fn test_refresh_thbgn() {
+        let pp = thbgn::paramgen::<P>();
+        let (sk, pk) = thbgn::keygen::<P>(pp);
+        let (sks0, commitments0) = thbgn::share_sk_verifiable::<P>(pp, sk, 3, 5).unwrap();
+        let (commitments1, sks1) = refresh_thbgn::<P>(pp, &commitments0, &sks0).unwrap();
+        // Refresh among the holders that are still around
+        let (commitments2, sks2) = refresh_thbgn::<P>(pp, &commitments1, &sks1[1..]).unwrap();
+
+        for (sk0, sk2) in sks0[1..].iter().zip(sks2.iter()) {
+            assert_eq!(sk0.0, sk2.0);
+            assert_ne!(sk0, sk2);
+            assert!(thbgn::verify_share::<P>(pp, pk, &commitments2, *sk2));
+            assert!(!thbgn::verify_share::<P>(pp, pk, &commitments2, *sk0));
+        }
+
+        let msg = F::from(3u64);
+        let ct = thbgn::lift::<P>(pp, thbgn::encrypt::<P>(pp, pk, msg));
+        let decrypt = |sks: &[thbgn::SecretKeyShare<P>]| {
+            let pdecs = sks
+                .iter()
+                .map(|sk| thbgn::partial_decrypt::<P>(pp, *sk, ct))
+                .collect::<Vec<_>>();
+            let inter = thbgn::intermediate_dec::<P>(pp, &pdecs, 3).unwrap();
+            let pdecs2 = sks
+                .iter()
+                .map(|sk| thbgn::partial_decrypt2::<P>(pp, *sk, inter))
+                .collect::<Vec<_>>();
+            thbgn::final_decrypt::<P>(pp, &pdecs2, 3, 1 << 4)
+        };
+        assert_eq!(decrypt(&sks2[..3]).unwrap(), msg);
+        // A share leaked before the refresh does not combine with the current ones
+        assert_ne!(decrypt(&[sks0[1], sks2[1], sks2[2]]), Ok(msg));
+    }
+
+    #[test]
+    fn test_reshare() {
+        let pp = thbgn::paramgen::<P>();
+        let (sk, pk) = thbgn::keygen::<P>(pp);
+        let (sks, commitments) = thbgn::share_sk_verifiable::<P>(pp, sk, 3, 5).unwrap();
+
+        // Holders 1 and 4 left, 2, 3 and 5 hand over to four new recipients
+        let old = [sks[1], sks[2], sks[4]];
+        let new_ids = [6, 7, 8, 9];
+        let (new_commitments, new_sks) =
+            reshare_thbgn::<P>(pp, &commitments, &old, 2, &new_ids).unwrap();
+        assert_eq!(new_commitments.0.threshold(), 2);
+        assert_eq!(new_sks.iter().map(|sk| sk.0).collect::<Vec<_>>(), new_ids);
+        assert!(
+            new_sks
+                .iter()
+                .all(|sk| thbgn::verify_share::<P>(pp, pk, &new_commitments, *sk))
+        );
+        let s1 = thbgn::reconstruct_secret_in_exp(
+            &new_sks[2..]
+                .iter()
+                .map(|(id, s1, _)| (*id, pp.0 * s1))
+                .collect::<Vec<_>>(),
+        )
+        .unwrap();
+        assert_eq!(s1, pk.0);
+
+        assert_eq!(
+            reshare_thbgn::<P>(pp, &commitments, &old[..2], 2, &new_ids).unwrap_err(),
+            DkgError::NotEnoughQualified(3, 2)
+        );
+
+        // An old holder dealing something else than its share is disqualified
+        let (g1, _) = pp;
+        let mut dealings = sks
+            .iter()
+            .map(|(id, s1, _)| {
+                let (shares, commitments) = vss::deal_to(g1, *s1, 2, &new_ids).unwrap();
+                Dealing {
+                    dealer: *id,
+                    commitments,
+                    shares,
+                }
+            })
+            .collect::<Vec<_>>();
+        let (bad_shares, bad_commitments) = vss::deal_to(g1, F::from(1u64), 2, &new_ids).unwrap();
+        dealings[0].commitments = bad_commitments;
+        dealings[0].shares = bad_shares;
+        let (new_commitments, _) =
+            reshare_with_dealings(g1, &commitments.0, &dealings, 2, &new_ids).unwrap();
+        assert_eq!(new_commitments.public_value(), pk.0);
+    }
+}
@@ -0,0 +1,202 @@
+use ark_ec::Group;
+use ark_serialize::{
+    CanonicalDeserialize, CanonicalSerialize, Compress, Read, SerializationError, Valid, Validate,
+    Write,
+};
+use std::collections::HashMap;
+
+/// Precomputed baby steps `base * i` for `i` in `[0, step)`, used to solve discrete logarithms
+/// in `(-bound, bound)` with at most `bound / step` giant steps (group additions).
+///
+/// The table only depends on `base` and `step`, so it can be computed once per public
+/// parameters, serialized, and reused across decryptions.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BabyStepTable<G: Group> {
+    base: G,
+    step: u64,
+    bound: u64,
+    /// Baby steps `i` by digest of `base * i`; a digest may collide, so all candidates are kept
+    index: HashMap<u64, Vec<u64>>,
+}
+
+/// Compact digest of a group element, computed from its canonical encoding.
+/// Distinct elements may share a digest, so every candidate is checked against the group element.
+fn digest<G: Group>(p: &G) -> u64 {
+    let mut bytes = Vec::new();
+    p.serialize_compressed(&mut bytes).unwrap();
+    bytes.chunks(8).fold(0u64, |acc, chunk| {
+        let mut word = [0u8; 8];
+        word[..chunk.len()].copy_from_slice(chunk);
+        acc ^ u64::from_le_bytes(word)
+    })
+}
+
+impl<G: Group> BabyStepTable<G> {
+    /// Table for logarithms in `(-bound, bound)` with `ceil(sqrt(bound))` baby steps.
+    pub fn new(base: G, bound: u64) -> Self {
+        let step = (bound as f64).sqrt().ceil().max(1.0) as u64;
+        Self::with_step(base, bound, step)
+    }
+
+    /// Table with a custom number of baby steps, trading memory for fewer giant steps.
+    pub fn with_step(base: G, bound: u64, step: u64) -> Self {
+        let step = step.max(1);
+        let mut index = HashMap::with_capacity(step as usize);
+        let mut p = G::zero();
+        for i in 0..step {
+            index.entry(digest(&p)).or_insert_with(Vec::new).push(i);
+            p += base;
+        }
+        Self {
+            base,
+            step,
+            bound,
+            index,
+        }
+    }
+
+    pub fn base(&self) -> G {
+        self.base
+    }
+
+    pub fn bound(&self) -> u64 {
+        self.bound
+    }
+
+    /// Find `x` in `[0, bound)` with `base * x == p`.
+    fn solve_unsigned(&self, p: G) -> Option<u64> {
+        let giant = -(self.base * G::ScalarField::from(self.step));
+        let mut gamma = p;
+        let mut j = 0u64;
+        while j * self.step < self.bound {
+            for i in self.index.get(&digest(&gamma)).into_iter().flatten() {
+                let x = j * self.step + i;
+                if x < self.bound && self.base * G::ScalarField::from(x) == p {
+                    return Some(x);
+                }
+            }
+            gamma += giant;
+            j += 1;
+        }
+        None
+    }
+
+    /// Find `x` in `(-bound, bound)` with `base * x == p`.
+    pub fn solve(&self, p: G) -> Option<G::ScalarField> {
+        if let Some(x) = self.solve_unsigned(p) {
+            return Some(G::ScalarField::from(x));
+        }
+        self.solve_unsigned(-p).map(|x| -G::ScalarField::from(x))
+    }
+}
+
+/// Find `x` in `(-bound, bound)` with `base * x == p`, building a throwaway table.
+pub fn find_dlog<G: Group>(base: G, p: G, bound: u64) -> Option<G::ScalarField> {
+    BabyStepTable::new(base, bound).solve(p)
+}
+
+impl<G: Group> CanonicalSerialize for BabyStepTable<G> {
+    fn serialize_with_mode<W: Write>(
+        &self,
+        mut writer: W,
+        compress: Compress,
+    ) -> Result<(), SerializationError> {
+        // Digests in baby-step order, so that the encoding is canonical
+        let mut digests = vec![0u64; self.step as usize];
+        for (d, is) in self.index.iter() {
+            for i in is {
+                digests[*i as usize] = *d;
+            }
+        }
+        self.base.serialize_with_mode(&mut writer, compress)?;
+        self.step.serialize_with_mode(&mut writer, compress)?;
+        self.bound.serialize_with_mode(&mut writer, compress)?;
+        digests.serialize_with_mode(&mut writer, compress)
+    }
+
+    fn serialized_size(&self, compress: Compress) -> usize {
+        self.base.serialized_size(compress) + 8 + 8 + 8 + 8 * self.step as usize
+    }
+}
+
+impl<G: Group> Valid for BabyStepTable<G> {
+    fn check(&self) -> Result<(), SerializationError> {
+        self.base.check()
+    }
+}
+
+impl<G: Group> CanonicalDeserialize for BabyStepTable<G> {
+    fn deserialize_with_mode<R: Read>(
+        mut reader: R,
+        compress: Compress,
+        validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        let base = G::deserialize_with_mode(&mut reader, compress, validate)?;
+        let step = u64::deserialize_with_mode(&mut reader, compress, validate)?;
+        let bound = u64::deserialize_with_mode(&mut reader, compress, validate)?;
+        let digests = Vec::<u64>::deserialize_with_mode(&mut reader, compress, validate)?;
+        if step == 0 || digests.len() as u64 != step {
+            return Err(SerializationError::InvalidData);
+        }
+        let mut index = HashMap::with_capacity(digests.len());
+        for (i, d) in digests.into_iter().enumerate() {
+            index.entry(d).or_insert_with(Vec::new).push(i as u64);
+        }
+        Ok(Self {
+            base,
+            step,
+            bound,
+            index,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ark_ec::pairing::{Pairing, PairingOutput};
+    use ark_std::UniformRand;
+    use rand::thread_rng;
+
+    type P = ark_bls12_381::Bls12_381;
+    type G1 = <P as Pairing>::G1;
+    type F = <P as Pairing>::ScalarField;
+
+    #[test]
+    fn test_bsgs_g1() {
+        let base = G1::rand(&mut thread_rng());
+        let table = BabyStepTable::new(base, 1 << 16);
+        for x in [0u64, 1, 255, 256, 257, 40_000, (1 << 16) - 1] {
+            assert_eq!(table.solve(base * F::from(x)), Some(F::from(x)));
+            assert_eq!(table.solve(-(base * F::from(x))), Some(-F::from(x)));
+        }
+        assert_eq!(table.solve(base * F::from(1u64 << 16)), None);
+    }
+
+    #[test]
+    fn test_bsgs_digest_collision() {
+        let base = G1::rand(&mut thread_rng());
+        let mut table = BabyStepTable::new(base, 1 << 8);
+        // Another baby step with the same digest as 5 must not hide it
+        let d = digest(&(base * F::from(5u64)));
+        table.index.get_mut(&d).unwrap().insert(0, 7);
+        assert_eq!(table.solve(base * F::from(5u64)), Some(F::from(5u64)));
+    }
+
+    #[test]
+    fn test_bsgs_gt_serialized() {
+        let mut rng = thread_rng();
+        let base = P::pairing(G1::rand(&mut rng), <P as Pairing>::G2::rand(&mut rng));
+        let table = BabyStepTable::<PairingOutput<P>>::with_step(base, 1 << 12, 16);
+
+        let mut bytes = Vec::new();
+        table.serialize_compressed(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), table.compressed_size());
+        let table2 = BabyStepTable::<PairingOutput<P>>::deserialize_compressed(&bytes[..]).unwrap();
+        assert_eq!(table, table2);
+
+        let x = F::from(3_000u64);
+        assert_eq!(table2.solve(base * x), Some(x));
+        assert_eq!(find_dlog(base, base * x, 1 << 12), Some(x));
+    }
+}
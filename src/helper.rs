@@ -0,0 +1,398 @@
+//! Helper role of the thHE protocols: decrypt and check recipient slots, evaluate f1 or f2 once per
+//! period and sign the result. Processed periods are persisted, so that restarting the helper does
+//! not let anyone obtain two evaluations for the same period.
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use ark_ec::Group;
+use ark_ec::pairing::Pairing;
+use tink_core::keyset;
+
+use crate::proofs::Transcript;
+use crate::protocol::{Audit, F1, F2, Protocol, Security};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HelperError {
+    /// The helper already evaluated this period
+    AlreadyProcessed(u16),
+    /// Missing or invalid auditor signature on the input ciphertexts
+    InvalidAuditorSignature,
+    /// (recipients, audits) of an approval that does not audit every recipient once
+    AuditCount(usize, usize),
+    /// Reading or writing the state file failed
+    Storage(std::io::ErrorKind),
+    /// The state file does not parse
+    CorruptedState,
+    /// A Tink primitive could not be built from a keyset
+    Keyset,
+    /// Signing the output failed
+    Signing,
+}
+
+impl std::fmt::Display for HelperError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HelperError::AlreadyProcessed(period) => {
+                write!(f, "period {} already processed", period)
+            }
+            HelperError::InvalidAuditorSignature => write!(f, "invalid auditor signature"),
+            HelperError::AuditCount(recipients, audits) => {
+                write!(f, "{} audits for {} recipients", audits, recipients)
+            }
+            HelperError::Storage(kind) => write!(f, "state storage failed: {}", kind),
+            HelperError::CorruptedState => write!(f, "corrupted state file"),
+            HelperError::Keyset => write!(f, "invalid keyset"),
+            HelperError::Signing => write!(f, "signing failed"),
+        }
+    }
+}
+
+impl std::error::Error for HelperError {}
+
+impl From<std::io::Error> for HelperError {
+    fn from(e: std::io::Error) -> Self {
+        HelperError::Storage(e.kind())
+    }
+}
+
+/// Message the auditor signs to approve the helper ciphertexts of a period, grouped by recipient,
+/// with what it checked about each recipient
+pub fn auditor_message(period: u16, ctxts: &[Vec<Vec<u8>>], audits: &[Audit]) -> [u8; 32] {
+    let mut transcript = Transcript::new(b"auditor approval");
+    transcript.append_bytes(&period.to_be_bytes());
+    transcript.append_bytes(&(ctxts.len() as u64).to_be_bytes());
+    transcript.append_bytes(&(audits.len() as u64).to_be_bytes());
+    for (ctxts_recipient, audit) in ctxts.iter().zip(audits) {
+        transcript.append_bytes(&(ctxts_recipient.len() as u64).to_be_bytes());
+        ctxts_recipient
+            .iter()
+            .for_each(|ct| transcript.append_bytes(ct));
+        transcript.append_bytes(&(audit.tags.len() as u64).to_be_bytes());
+        audit
+            .tags
+            .iter()
+            .for_each(|tag| transcript.append_bytes(tag));
+        transcript.append_bytes(&audit.entitlement.to_be_bytes());
+    }
+    transcript.digest()
+}
+
+/// Signed output of a period
+pub struct Evaluation<S: Protocol> {
+    pub output: S::HelperOutput,
+    /// Slots the output was evaluated on, which recipients check before partially decrypting
+    pub inputs: Vec<S::Slot>,
+    pub signature: Vec<u8>,
+    /// Recipients, by index in the processed list, dropped because one of their slots does not
+    /// decrypt or decode
+    pub rejected: Vec<usize>,
+}
+
+pub struct Helper {
+    security: Security,
+    sk_enc: keyset::Handle,
+    sk_sig: keyset::Handle,
+    vk_sig_auditor: Option<keyset::Handle>,
+    state_path: PathBuf,
+    /// Input digest of each processed period
+    processed: BTreeMap<u16, [u8; 32]>,
+}
+
+impl Helper {
+    /// Helper whose processed periods are stored at `state_path`, loaded if the file exists
+    pub fn open(
+        state_path: impl AsRef<Path>,
+        security: Security,
+        sk_enc: keyset::Handle,
+        sk_sig: keyset::Handle,
+    ) -> Result<Self, HelperError> {
+        tink_hybrid::init();
+        tink_signature::init();
+        let state_path = state_path.as_ref().to_path_buf();
+        let processed = match std::fs::read(&state_path) {
+            Ok(bytes) => bincode::deserialize(&bytes).map_err(|_| HelperError::CorruptedState)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Helper {
+            security,
+            sk_enc,
+            sk_sig,
+            vk_sig_auditor: None,
+            state_path,
+            processed,
+        })
+    }
+
+    /// Only process inputs approved by the auditor
+    pub fn with_auditor(mut self, vk_sig_auditor: keyset::Handle) -> Self {
+        self.vk_sig_auditor = Some(vk_sig_auditor);
+        self
+    }
+
+    /// Input digest of `period`, if it was processed
+    pub fn processed(&self, period: u16) -> Option<[u8; 32]> {
+        self.processed.get(&period).copied()
+    }
+
+    fn persist(&self) -> Result<(), HelperError> {
+        let bytes = bincode::serialize(&self.processed).map_err(|_| HelperError::CorruptedState)?;
+        // Write then rename, so that a crash never leaves a truncated state file
+        let tmp = self.state_path.with_extension("tmp");
+        std::fs::write(&tmp, bytes)?;
+        std::fs::rename(&tmp, &self.state_path)?;
+        Ok(())
+    }
+
+    /// Evaluate the helper ciphertexts `ctxts` of `period`, grouped by recipient, and sign the
+    /// output. A helper with an auditor needs the audit of each recipient and the auditor's
+    /// signature on them. A recipient is dropped, and reported, if any of their slots does not
+    /// decrypt or decode against their audit.
+    pub fn process<S: Protocol>(
+        &mut self,
+        pp: S::PublicParameters,
+        pk: S::PublicKey,
+        period: u16,
+        ctxts: &[Vec<Vec<u8>>],
+        approval: Option<(&[Audit], &[u8])>,
+    ) -> Result<Evaluation<S>, HelperError> {
+        if self.processed.contains_key(&period) {
+            return Err(HelperError::AlreadyProcessed(period));
+        }
+        let audits = match &self.vk_sig_auditor {
+            None => None,
+            Some(vk_sig_auditor) => {
+                let v = tink_signature::new_verifier(vk_sig_auditor)
+                    .map_err(|_| HelperError::Keyset)?;
+                let (audits, sig_auditor) = approval.ok_or(HelperError::InvalidAuditorSignature)?;
+                if audits.len() != ctxts.len() {
+                    return Err(HelperError::AuditCount(ctxts.len(), audits.len()));
+                }
+                v.verify(sig_auditor, &auditor_message(period, ctxts, audits))
+                    .map_err(|_| HelperError::InvalidAuditorSignature)?;
+                Some(audits)
+            }
+        };
+
+        let dec = tink_hybrid::new_decrypt(&self.sk_enc).map_err(|_| HelperError::Keyset)?;
+        let mut slots = Vec::new();
+        let mut rejected = Vec::new();
+        for (i, ctxts_recipient) in ctxts.iter().enumerate() {
+            let decoded = ctxts_recipient
+                .iter()
+                .map(|ct| dec.decrypt(ct, &period.to_be_bytes()).ok())
+                .collect::<Option<Vec<_>>>()
+                .and_then(|pts| {
+                    let audit = audits.map(|audits| &audits[i]);
+                    S::decode_slots(pp, pk, period, &pts, audit, self.security)
+                });
+            match decoded {
+                Some(slots_recipient) => slots.extend(slots_recipient),
+                None => rejected.push(i),
+            }
+        }
+        let output = S::evaluate(pp, period, &slots);
+        let signer = tink_signature::new_signer(&self.sk_sig).map_err(|_| HelperError::Keyset)?;
+        let signature = signer
+            .sign(&S::output_to_bytes(&output))
+            .map_err(|_| HelperError::Signing)?;
+
+        // Record the period before releasing the signed output, and only once signing succeeded so
+        // that a signing failure does not use up the period
+        self.processed.insert(period, S::output_digest(&output));
+        if let Err(e) = self.persist() {
+            self.processed.remove(&period);
+            return Err(e);
+        }
+        Ok(Evaluation {
+            output,
+            inputs: slots,
+            signature,
+            rejected,
+        })
+    }
+
+    /// f1: sum of the indicator bits of all slots
+    pub fn process_f1<G: Group>(
+        &mut self,
+        pp: <F1<G> as Protocol>::PublicParameters,
+        pk: <F1<G> as Protocol>::PublicKey,
+        period: u16,
+        ctxts: &[Vec<Vec<u8>>],
+        approval: Option<(&[Audit], &[u8])>,
+    ) -> Result<Evaluation<F1<G>>, HelperError> {
+        self.process::<F1<G>>(pp, pk, period, ctxts, approval)
+    }
+
+    /// f2: product of the indicator and data of each slot
+    pub fn process_f2<P: Pairing>(
+        &mut self,
+        pp: <F2<P> as Protocol>::PublicParameters,
+        pk: <F2<P> as Protocol>::PublicKey,
+        period: u16,
+        ctxts: &[Vec<Vec<u8>>],
+        approval: Option<(&[Audit], &[u8])>,
+    ) -> Result<Evaluation<F2<P>>, HelperError> {
+        self.process::<F2<P>>(pp, pk, period, ctxts, approval)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::recipient::Recipient;
+    use crate::test_util::keys;
+    use crate::{MAX_ENTITLEMENT, MIN_ENTITLEMENT, TAG_BYTELEN, thbgn, thelgamal};
+
+    type P = ark_bls12_381::Bls12_381;
+    type G1 = <P as Pairing>::G1;
+    type F = <P as Pairing>::ScalarField;
+
+    fn state_path(name: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("helper-{}-{}.state", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn test_helper_f1() {
+        let path = state_path("f1");
+        let pp = thelgamal::paramgen::<G1>();
+        let (sk, pk) = thelgamal::keygen::<G1>(pp);
+        let shares = thelgamal::share_sk::<G1>(sk, 2, 4).unwrap();
+        let (sk_enc, sk_sig) = keys();
+        let (_, sk_sig_auditor) = keys();
+        let recipients = shares
+            .iter()
+            .enumerate()
+            .map(|(i, share)| {
+                Recipient::<F1<G1>>::new(
+                    i,
+                    Security::Malicious,
+                    pp,
+                    pk,
+                    *share,
+                    sk_enc.public().unwrap(),
+                    sk_sig.public().unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+        let mut ctxts = recipients
+            .iter()
+            .map(|r| r.submit(1, r.id() != 1, 0).unwrap().helper_ctxts)
+            .collect::<Vec<_>>();
+        // A malformed slot drops its whole recipient
+        ctxts[0].push(b"garbage".to_vec());
+        // Recipients without tags bind their submission to the zero tag
+        let mut audits = vec![
+            Audit {
+                tags: vec![[0u8; TAG_BYTELEN]],
+                entitlement: MIN_ENTITLEMENT as u64,
+            };
+            4
+        ];
+        // A submission bound to a tag the auditor did not check drops its recipient
+        audits[1].tags = vec![[1u8; TAG_BYTELEN]];
+        // So does a submission proving another entitlement than the audited one
+        audits[3].entitlement = MAX_ENTITLEMENT as u64;
+        let auditor = tink_signature::new_signer(&sk_sig_auditor).unwrap();
+        let sig_auditor = auditor.sign(&auditor_message(1, &ctxts, &audits)).unwrap();
+
+        let mut helper = Helper::open(&path, Security::Malicious, sk_enc, sk_sig)
+            .unwrap()
+            .with_auditor(sk_sig_auditor.public().unwrap());
+        assert_eq!(
+            helper
+                .process_f1::<G1>(pp, pk, 1, &ctxts, Some((&audits, &[0u8; 8])))
+                .err(),
+            Some(HelperError::InvalidAuditorSignature)
+        );
+        assert_eq!(
+            helper
+                .process_f1::<G1>(pp, pk, 1, &ctxts, Some((&audits[1..], &sig_auditor)))
+                .err(),
+            Some(HelperError::AuditCount(4, 3))
+        );
+        let Evaluation {
+            output,
+            inputs,
+            signature,
+            rejected,
+        } = helper
+            .process_f1::<G1>(pp, pk, 1, &ctxts, Some((&audits, &sig_auditor)))
+            .unwrap();
+        assert_eq!(rejected, vec![0, 1, 3]);
+        assert_eq!(
+            thelgamal::decrypt::<G1>(pp, sk, output.output, 8).unwrap(),
+            F::from(1u64)
+        );
+        let msg = recipients[0]
+            .partially_decrypt(1, &inputs, &output, &signature)
+            .unwrap();
+        assert_eq!(
+            msg.pdec.0,
+            thelgamal::partial_decrypt::<G1>(pp, shares[0], output.output)
+        );
+
+        // Replays are refused, also after a restart
+        assert_eq!(
+            helper
+                .process_f1::<G1>(pp, pk, 1, &ctxts, Some((&audits, &sig_auditor)))
+                .err(),
+            Some(HelperError::AlreadyProcessed(1))
+        );
+        let (sk_enc, sk_sig) = keys();
+        let mut helper = Helper::open(&path, Security::Malicious, sk_enc, sk_sig).unwrap();
+        assert_eq!(helper.processed(1), Some(output.inputs_digest));
+        assert_eq!(
+            helper.process_f1::<G1>(pp, pk, 1, &ctxts, None).err(),
+            Some(HelperError::AlreadyProcessed(1))
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_helper_f2() {
+        let path = state_path("f2");
+        let pp = thbgn::paramgen::<P>();
+        let (sk, pk) = thbgn::keygen::<P>(pp);
+        let shares = thbgn::share_sk::<P>(sk, 2, 3).unwrap();
+        let (sk_enc, sk_sig) = keys();
+        let recipient = Recipient::<F2<P>>::new(
+            0,
+            Security::HonestButCurious,
+            pp,
+            pk,
+            shares[0],
+            sk_enc.public().unwrap(),
+            sk_sig.public().unwrap(),
+        );
+        let ctxts = vec![recipient.submit(2, true, 5).unwrap().helper_ctxts];
+
+        let mut helper = Helper::open(&path, Security::HonestButCurious, sk_enc, sk_sig).unwrap();
+        let Evaluation {
+            output,
+            inputs,
+            signature,
+            rejected,
+        } = helper.process_f2::<P>(pp, pk, 2, &ctxts, None).unwrap();
+        assert!(rejected.is_empty());
+        assert_eq!(output.output.len(), 1);
+        assert_eq!(
+            thbgn::decrypt::<P>(pp, sk, output.output[0][0], 8).unwrap(),
+            F::from(5u64)
+        );
+        assert!(
+            recipient
+                .partially_decrypt(2, &inputs, &output, &signature)
+                .is_ok()
+        );
+        assert!(helper.process_f2::<P>(pp, pk, 3, &ctxts, None).is_ok());
+        assert_eq!(
+            helper.process_f2::<P>(pp, pk, 2, &ctxts, None).err(),
+            Some(HelperError::AlreadyProcessed(2))
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+}
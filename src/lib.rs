@@ -4,8 +4,22 @@ use log::info;
 use rand::seq::index::sample;
 use env_logger;
 
+pub mod auditor;
+pub mod dkg;
+pub mod dlog;
+pub mod helper;
+pub mod proofs;
+pub mod protocol;
+pub mod recipient;
+pub mod station;
+pub mod submission;
+pub mod tags;
+#[cfg(test)]
+mod test_util;
 #[allow(warnings)]
 pub mod thbgn;
+pub mod thelgamal;
+pub mod vss;
 
 pub const NUM_RECIPIENTS: usize = 10_000;
 pub const NUM_PERIODS: usize = 10;
@@ -17,5 +31,6 @@ pub const CONTACT_INFO_LEN: usize = 10;
 pub const TOTAL_SHOWUP_ENTITLEMENT: usize = 20_000;
 pub const TOTAL_ENTITLEMENT: usize = 18_000;
 pub const TAG_BYTELEN: usize = 16; // 128 bits
+pub const DATA_BITS: usize = 10; // f2 data fields are in [0, 2^DATA_BITS)
 
 const PROB_IN_GROUP: f64 = 0.10;
\ No newline at end of file
@@ -0,0 +1,519 @@
+use ark_ec::Group;
+use ark_ff::PrimeField;
+use ark_serialize::{
+    CanonicalDeserialize, CanonicalSerialize, Compress, Read, SerializationError, Valid, Validate,
+    Write,
+};
+use ark_std::UniformRand;
+use openssl::sha::Sha256;
+use rand::thread_rng;
+
+/// Fiat-Shamir transcript: a domain-separated SHA-256 hash of everything the prover committed to
+pub struct Transcript(Sha256);
+
+impl Transcript {
+    pub fn new(label: &[u8]) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(&(label.len() as u64).to_le_bytes());
+        hasher.update(label);
+        Transcript(hasher)
+    }
+
+    pub fn append<T: CanonicalSerialize + ?Sized>(&mut self, item: &T) {
+        let mut bytes = Vec::new();
+        item.serialize_compressed(&mut bytes).unwrap();
+        self.append_bytes(&bytes);
+    }
+
+    pub fn append_bytes(&mut self, bytes: &[u8]) {
+        self.0.update(&(bytes.len() as u64).to_le_bytes());
+        self.0.update(bytes);
+    }
+
+    pub fn challenge<F: PrimeField>(self) -> F {
+        F::from_le_bytes_mod_order(&self.digest())
+    }
+
+    pub fn digest(self) -> [u8; 32] {
+        self.0.finish()
+    }
+}
+
+/// Chaum-Pedersen proof that the same x satisfies Y = x B for several (B, Y), in challenge-response
+/// form.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DleqProof<F: PrimeField> {
+    pub challenge: F,
+    pub response: F,
+}
+
+fn dleq_transcript<A: Group, B: Group<ScalarField = A::ScalarField>>(
+    label: &[u8],
+    a: (A, A),
+    bs: &[(B, B)],
+    commitment_a: A,
+    commitments_b: &[B],
+) -> Transcript {
+    let mut transcript = Transcript::new(label);
+    transcript.append(&a);
+    for b in bs {
+        transcript.append(b);
+    }
+    transcript.append(&commitment_a);
+    for t in commitments_b {
+        transcript.append(t);
+    }
+    transcript
+}
+
+/// Prove knowledge of x with a.1 = x a.0 and b.1 = x b.0 for each b in `bs`. The first pair lives
+/// in a group A, the others in a group B of the same order, e.g. a verification key in G1 and
+/// partial decryptions in GT.
+pub fn prove_dleq<A: Group, B: Group<ScalarField = A::ScalarField>>(
+    label: &[u8],
+    a: (A, A),
+    bs: &[(B, B)],
+    x: A::ScalarField,
+) -> DleqProof<A::ScalarField> {
+    let mut rng = thread_rng();
+    let k = A::ScalarField::rand(&mut rng);
+    let commitments_b = bs.iter().map(|(base, _)| *base * k).collect::<Vec<_>>();
+    let challenge = dleq_transcript(label, a, bs, a.0 * k, &commitments_b).challenge();
+    DleqProof {
+        challenge,
+        response: k + challenge * x,
+    }
+}
+
+pub fn verify_dleq<A: Group, B: Group<ScalarField = A::ScalarField>>(
+    label: &[u8],
+    a: (A, A),
+    bs: &[(B, B)],
+    proof: &DleqProof<A::ScalarField>,
+) -> bool {
+    let DleqProof {
+        challenge,
+        response,
+    } = *proof;
+    let commitment_a = a.0 * response - a.1 * challenge;
+    let commitments_b = bs
+        .iter()
+        .map(|(base, image)| *base * response - *image * challenge)
+        .collect::<Vec<_>>();
+    dleq_transcript(label, a, bs, commitment_a, &commitments_b).challenge::<A::ScalarField>()
+        == challenge
+}
+
+/// Public bases (g, h) and ciphertext (c1, c2) = (r g, m g + r h) of an exponential ElGamal
+/// encryption
+pub type ElGamalInstance<G> = (G, G, G, G);
+
+/// CDS OR-proof that exponential ElGamal ciphertexts, in up to two groups of the same order, all
+/// encrypt the same bit m.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BitProof<F: PrimeField> {
+    /// Challenges of the branches m = 0 and m = 1, summing to the Fiat-Shamir challenge
+    pub challenges: [F; 2],
+    /// Responses of each branch, one per ciphertext
+    pub responses: [Vec<F>; 2],
+}
+
+/// Commitments of the branch for plaintext m, recomputed from its challenge and responses
+fn bit_commitments<G: Group>(
+    instances: &[ElGamalInstance<G>],
+    m: G::ScalarField,
+    challenge: G::ScalarField,
+    responses: &[G::ScalarField],
+) -> Vec<(G, G)> {
+    instances
+        .iter()
+        .zip(responses)
+        .map(|((g, h, c1, c2), z)| {
+            (
+                *g * z - *c1 * challenge,
+                *h * z - (*c2 - *g * m) * challenge,
+            )
+        })
+        .collect()
+}
+
+fn bit_transcript<A: Group, B: Group<ScalarField = A::ScalarField>>(
+    label: &[u8],
+    a: &[ElGamalInstance<A>],
+    b: &[ElGamalInstance<B>],
+    commitments: [(Vec<(A, A)>, Vec<(B, B)>); 2],
+) -> Transcript {
+    let mut transcript = Transcript::new(label);
+    a.iter().for_each(|x| transcript.append(x));
+    b.iter().for_each(|x| transcript.append(x));
+    for (commitments_a, commitments_b) in commitments.iter() {
+        commitments_a.iter().for_each(|x| transcript.append(x));
+        commitments_b.iter().for_each(|x| transcript.append(x));
+    }
+    transcript
+}
+
+/// Prove that the ciphertexts in `a` and `b` all encrypt `bit`, where `randomness` holds the r
+/// of each ciphertext, those of `a` first.
+pub fn prove_bit<A: Group, B: Group<ScalarField = A::ScalarField>>(
+    label: &[u8],
+    a: &[ElGamalInstance<A>],
+    b: &[ElGamalInstance<B>],
+    bit: bool,
+    randomness: &[A::ScalarField],
+) -> BitProof<A::ScalarField> {
+    assert_eq!(randomness.len(), a.len() + b.len());
+    let mut rng = thread_rng();
+    let (real, fake) = if bit { (1, 0) } else { (0, 1) };
+
+    // Simulate the false branch with a random challenge and random responses
+    let fake_challenge = A::ScalarField::rand(&mut rng);
+    let fake_responses = (0..randomness.len())
+        .map(|_| A::ScalarField::rand(&mut rng))
+        .collect::<Vec<_>>();
+    let m_fake = A::ScalarField::from(fake as u64);
+    let fake_commitments = (
+        bit_commitments(a, m_fake, fake_challenge, &fake_responses[..a.len()]),
+        bit_commitments(b, m_fake, fake_challenge, &fake_responses[a.len()..]),
+    );
+
+    // Commit honestly in the true branch
+    let ks = (0..randomness.len())
+        .map(|_| A::ScalarField::rand(&mut rng))
+        .collect::<Vec<_>>();
+    let real_commitments = (
+        a.iter()
+            .zip(&ks)
+            .map(|((g, h, _, _), k)| (*g * k, *h * k))
+            .collect::<Vec<_>>(),
+        b.iter()
+            .zip(&ks[a.len()..])
+            .map(|((g, h, _, _), k)| (*g * k, *h * k))
+            .collect::<Vec<_>>(),
+    );
+
+    let commitments = if bit {
+        [fake_commitments, real_commitments]
+    } else {
+        [real_commitments, fake_commitments]
+    };
+    let challenge: A::ScalarField = bit_transcript(label, a, b, commitments).challenge();
+    let real_challenge = challenge - fake_challenge;
+    let real_responses = ks
+        .iter()
+        .zip(randomness)
+        .map(|(k, r)| *k + real_challenge * r)
+        .collect::<Vec<_>>();
+
+    let mut challenges = [A::ScalarField::from(0u64); 2];
+    challenges[real] = real_challenge;
+    challenges[fake] = fake_challenge;
+    let mut responses = [Vec::new(), Vec::new()];
+    responses[real] = real_responses;
+    responses[fake] = fake_responses;
+    BitProof {
+        challenges,
+        responses,
+    }
+}
+
+pub fn verify_bit<A: Group, B: Group<ScalarField = A::ScalarField>>(
+    label: &[u8],
+    a: &[ElGamalInstance<A>],
+    b: &[ElGamalInstance<B>],
+    proof: &BitProof<A::ScalarField>,
+) -> bool {
+    let n = a.len() + b.len();
+    if proof.responses.iter().any(|r| r.len() != n) {
+        return false;
+    }
+    let commitments = [0u64, 1].map(|m| {
+        let m = m as usize;
+        let (challenge, responses) = (proof.challenges[m], &proof.responses[m]);
+        let m = A::ScalarField::from(m as u64);
+        (
+            bit_commitments(a, m, challenge, &responses[..a.len()]),
+            bit_commitments(b, m, challenge, &responses[a.len()..]),
+        )
+    });
+    bit_transcript(label, a, b, commitments).challenge::<A::ScalarField>()
+        == proof.challenges[0] + proof.challenges[1]
+}
+
+/// Proof of knowledge of (m, r_a, r_b) such that an exponential ElGamal ciphertext in group A
+/// with randomness r_a and one in group B with randomness r_b both encrypt m.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EqualityProof<F: PrimeField> {
+    pub challenge: F,
+    /// Responses for m, r_a and r_b
+    pub responses: [F; 3],
+}
+
+/// Commitments (k_r g, k_m g + k_r h) of one instance
+fn equality_commitments<G: Group>(
+    (g, h, _, _): &ElGamalInstance<G>,
+    k_m: G::ScalarField,
+    k_r: G::ScalarField,
+) -> (G, G) {
+    (*g * k_r, *g * k_m + *h * k_r)
+}
+
+fn equality_transcript<A: Group, B: Group<ScalarField = A::ScalarField>>(
+    label: &[u8],
+    a: &ElGamalInstance<A>,
+    b: &ElGamalInstance<B>,
+    commitment_a: (A, A),
+    commitment_b: (B, B),
+) -> Transcript {
+    let mut transcript = Transcript::new(label);
+    transcript.append(a);
+    transcript.append(b);
+    transcript.append(&commitment_a);
+    transcript.append(&commitment_b);
+    transcript
+}
+
+/// Prove that `a` and `b` both encrypt `m`, with randomness `r_a` and `r_b` respectively
+pub fn prove_equality<A: Group, B: Group<ScalarField = A::ScalarField>>(
+    label: &[u8],
+    a: &ElGamalInstance<A>,
+    b: &ElGamalInstance<B>,
+    m: A::ScalarField,
+    r_a: A::ScalarField,
+    r_b: A::ScalarField,
+) -> EqualityProof<A::ScalarField> {
+    let mut rng = thread_rng();
+    let (k_m, k_a, k_b) = (
+        A::ScalarField::rand(&mut rng),
+        A::ScalarField::rand(&mut rng),
+        A::ScalarField::rand(&mut rng),
+    );
+    let challenge: A::ScalarField = equality_transcript(
+        label,
+        a,
+        b,
+        equality_commitments(a, k_m, k_a),
+        equality_commitments(b, k_m, k_b),
+    )
+    .challenge();
+    EqualityProof {
+        challenge,
+        responses: [
+            k_m + challenge * m,
+            k_a + challenge * r_a,
+            k_b + challenge * r_b,
+        ],
+    }
+}
+
+pub fn verify_equality<A: Group, B: Group<ScalarField = A::ScalarField>>(
+    label: &[u8],
+    a: &ElGamalInstance<A>,
+    b: &ElGamalInstance<B>,
+    proof: &EqualityProof<A::ScalarField>,
+) -> bool {
+    let e = proof.challenge;
+    let [z_m, z_a, z_b] = proof.responses;
+    let (t1, t2) = equality_commitments(a, z_m, z_a);
+    let (t3, t4) = equality_commitments(b, z_m, z_b);
+    let commitment_a = (t1 - a.2 * e, t2 - a.3 * e);
+    let commitment_b = (t3 - b.2 * e, t4 - b.3 * e);
+    equality_transcript(label, a, b, commitment_a, commitment_b).challenge::<A::ScalarField>() == e
+}
+
+impl<F: PrimeField> CanonicalSerialize for DleqProof<F> {
+    fn serialize_with_mode<W: Write>(
+        &self,
+        mut writer: W,
+        compress: Compress,
+    ) -> Result<(), SerializationError> {
+        self.challenge.serialize_with_mode(&mut writer, compress)?;
+        self.response.serialize_with_mode(&mut writer, compress)
+    }
+
+    fn serialized_size(&self, compress: Compress) -> usize {
+        self.challenge.serialized_size(compress) + self.response.serialized_size(compress)
+    }
+}
+
+impl<F: PrimeField> Valid for DleqProof<F> {
+    fn check(&self) -> Result<(), SerializationError> {
+        Ok(())
+    }
+}
+
+impl<F: PrimeField> CanonicalDeserialize for DleqProof<F> {
+    fn deserialize_with_mode<R: Read>(
+        mut reader: R,
+        compress: Compress,
+        validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        Ok(DleqProof {
+            challenge: F::deserialize_with_mode(&mut reader, compress, validate)?,
+            response: F::deserialize_with_mode(&mut reader, compress, validate)?,
+        })
+    }
+}
+
+impl<F: PrimeField> CanonicalSerialize for EqualityProof<F> {
+    fn serialize_with_mode<W: Write>(
+        &self,
+        mut writer: W,
+        compress: Compress,
+    ) -> Result<(), SerializationError> {
+        self.challenge.serialize_with_mode(&mut writer, compress)?;
+        for z in self.responses.iter() {
+            z.serialize_with_mode(&mut writer, compress)?;
+        }
+        Ok(())
+    }
+
+    fn serialized_size(&self, compress: Compress) -> usize {
+        self.challenge.serialized_size(compress)
+            + self
+                .responses
+                .iter()
+                .map(|z| z.serialized_size(compress))
+                .sum::<usize>()
+    }
+}
+
+impl<F: PrimeField> Valid for EqualityProof<F> {
+    fn check(&self) -> Result<(), SerializationError> {
+        Ok(())
+    }
+}
+
+impl<F: PrimeField> CanonicalDeserialize for EqualityProof<F> {
+    fn deserialize_with_mode<R: Read>(
+        mut reader: R,
+        compress: Compress,
+        validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        let challenge = F::deserialize_with_mode(&mut reader, compress, validate)?;
+        let mut responses = [F::from(0u64); 3];
+        for z in responses.iter_mut() {
+            *z = F::deserialize_with_mode(&mut reader, compress, validate)?;
+        }
+        Ok(EqualityProof {
+            challenge,
+            responses,
+        })
+    }
+}
+
+impl<F: PrimeField> CanonicalSerialize for BitProof<F> {
+    fn serialize_with_mode<W: Write>(
+        &self,
+        mut writer: W,
+        compress: Compress,
+    ) -> Result<(), SerializationError> {
+        self.challenges[0].serialize_with_mode(&mut writer, compress)?;
+        self.challenges[1].serialize_with_mode(&mut writer, compress)?;
+        self.responses[0].serialize_with_mode(&mut writer, compress)?;
+        self.responses[1].serialize_with_mode(&mut writer, compress)
+    }
+
+    fn serialized_size(&self, compress: Compress) -> usize {
+        self.challenges[0].serialized_size(compress)
+            + self.challenges[1].serialized_size(compress)
+            + self.responses[0].serialized_size(compress)
+            + self.responses[1].serialized_size(compress)
+    }
+}
+
+impl<F: PrimeField> Valid for BitProof<F> {
+    fn check(&self) -> Result<(), SerializationError> {
+        Ok(())
+    }
+}
+
+impl<F: PrimeField> CanonicalDeserialize for BitProof<F> {
+    fn deserialize_with_mode<R: Read>(
+        mut reader: R,
+        compress: Compress,
+        validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        let c0 = F::deserialize_with_mode(&mut reader, compress, validate)?;
+        let c1 = F::deserialize_with_mode(&mut reader, compress, validate)?;
+        let r0 = Vec::<F>::deserialize_with_mode(&mut reader, compress, validate)?;
+        let r1 = Vec::<F>::deserialize_with_mode(&mut reader, compress, validate)?;
+        Ok(BitProof {
+            challenges: [c0, c1],
+            responses: [r0, r1],
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ark_ec::pairing::{Pairing, PairingOutput};
+
+    type P = ark_bls12_381::Bls12_381;
+    type G1 = <P as Pairing>::G1;
+    type F = <P as Pairing>::ScalarField;
+
+    #[test]
+    fn test_dleq() {
+        let mut rng = thread_rng();
+        let g1 = G1::rand(&mut rng);
+        let bases = (0..2)
+            .map(|_| PairingOutput::<P>::rand(&mut rng))
+            .collect::<Vec<_>>();
+        let x = F::rand(&mut rng);
+        let a = (g1, g1 * x);
+        let bs = bases.iter().map(|b| (*b, *b * x)).collect::<Vec<_>>();
+
+        let proof = prove_dleq(b"test", a, &bs, x);
+        assert!(verify_dleq(b"test", a, &bs, &proof));
+        assert!(!verify_dleq(b"other", a, &bs, &proof));
+
+        let mut bad = bs.clone();
+        bad[1].1 += bases[1];
+        assert!(!verify_dleq(b"test", a, &bad, &proof));
+        let proof = prove_dleq(b"test", a, &bad, x);
+        assert!(!verify_dleq(b"test", a, &bad, &proof));
+    }
+
+    #[test]
+    fn test_bit_proof() {
+        let mut rng = thread_rng();
+        let (g, h) = (G1::rand(&mut rng), G1::rand(&mut rng));
+        let (g2, h2) = (
+            <P as Pairing>::G2::rand(&mut rng),
+            <P as Pairing>::G2::rand(&mut rng),
+        );
+        let enc = |m: u64, r: F| (g, h, g * r, g * F::from(m) + h * r);
+        let enc2 = |m: u64, r: F| (g2, h2, g2 * r, g2 * F::from(m) + h2 * r);
+        let (r, r2) = (F::rand(&mut rng), F::rand(&mut rng));
+
+        for m in [0u64, 1] {
+            let a = [enc(m, r)];
+            let b = [enc2(m, r2)];
+            let proof = prove_bit(b"test", &a, &b, m == 1, &[r, r2]);
+            assert!(verify_bit(b"test", &a, &b, &proof));
+            assert!(!verify_bit(b"other", &a, &b, &proof));
+
+            let mut bytes = Vec::new();
+            proof.serialize_compressed(&mut bytes).unwrap();
+            assert_eq!(
+                BitProof::<F>::deserialize_compressed(&bytes[..]).unwrap(),
+                proof
+            );
+        }
+
+        // Not a bit, or different bits in the two groups
+        let a = [enc(2, r)];
+        let proof = prove_bit::<G1, G1>(b"test", &a, &[], false, &[r]);
+        assert!(!verify_bit::<G1, G1>(b"test", &a, &[], &proof));
+        let proof = prove_bit::<G1, G1>(b"test", &a, &[], true, &[r]);
+        assert!(!verify_bit::<G1, G1>(b"test", &a, &[], &proof));
+        let a = [enc(0, r)];
+        let b = [enc2(1, r2)];
+        for bit in [false, true] {
+            let proof = prove_bit(b"test", &a, &b, bit, &[r, r2]);
+            assert!(!verify_bit(b"test", &a, &b, &proof));
+        }
+    }
+}
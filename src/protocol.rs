@@ -0,0 +1,812 @@
+//! The two functions the helper evaluates on recipient inputs: f1 sums encrypted indicator bits
+//! under threshold ElGamal, f2 multiplies each indicator with the recipient's data under threshold
+//! BGN.
+use std::convert::Infallible;
+use std::marker::PhantomData;
+
+use ark_ec::Group;
+use ark_ec::pairing::Pairing;
+use ark_ff::PrimeField;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use secret_sharing_and_dkg::common::ShareId;
+
+use crate::dlog::BabyStepTable;
+use crate::proofs::BitProof;
+use crate::submission::{self, RecipientSubmission};
+use crate::{DATA_BITS, MAX_ENTITLEMENT, TAG_BYTELEN};
+use crate::{thbgn, thelgamal};
+
+/// Honest-but-curious variants send bare ciphertexts. Malicious variants send the slots of a
+/// recipient as a `RecipientSubmission`, proving each slot a bit and that the slots add up to 0 or
+/// the recipient's entitlement, and check proofs of partial decryption.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Security {
+    HonestButCurious,
+    Malicious,
+}
+
+/// What the auditor checked about the slots of one recipient
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Audit {
+    /// Secret tags the slots came with, one of which a malicious submission must be bound to
+    pub tags: Vec<[u8; TAG_BYTELEN]>,
+    /// Entitlement registered for the owner of the tags, which a malicious submission must prove
+    pub entitlement: u64,
+}
+
+pub trait Protocol {
+    type PublicParameters: Copy;
+    type PublicKey: Copy;
+    type SecretKeyShare: Copy;
+    /// Ciphertexts of one slot, as the helper evaluates them
+    type Slot;
+    type HelperOutput: Clone;
+    type PartialDecryption;
+    /// Public data the second decryption round starts from, Infallible for single-round schemes
+    type Intermediate: Clone;
+    type PartialDecryption2;
+    /// What a partial decryption is checked against
+    type VerificationKey: Copy;
+    type DlogTable;
+    type Plaintext;
+
+    /// Plaintexts sent to the helper for the slots of one recipient, with `indicator` in the first
+    /// `entitlement` slots, `data` in the first slot and dummy zeros elsewhere. There is one slot
+    /// per secret tag, at least one, except in the malicious variant: there the MAX_ENTITLEMENT
+    /// slots form a `RecipientSubmission` bound to `period`, the first tag and `entitlement`. None
+    /// if `data` is not in [0, 2^DATA_BITS) or, in the malicious variant, if `entitlement` is not
+    /// in [MIN_ENTITLEMENT, MAX_ENTITLEMENT].
+    #[allow(clippy::too_many_arguments)]
+    fn encode_slots(
+        pp: Self::PublicParameters,
+        pk: Self::PublicKey,
+        period: u16,
+        tags: &[[u8; TAG_BYTELEN]],
+        entitlement: u64,
+        indicator: bool,
+        data: u64,
+        security: Security,
+    ) -> Option<Vec<Vec<u8>>>;
+
+    /// Inverse of `encode_slots` for the slots of one recipient, None if a plaintext is malformed
+    /// or, in the malicious variant, if the submission does not verify or is not bound to one of
+    /// the tags and to the entitlement in `audit`
+    fn decode_slots(
+        pp: Self::PublicParameters,
+        pk: Self::PublicKey,
+        period: u16,
+        pts: &[Vec<u8>],
+        audit: Option<&Audit>,
+        security: Security,
+    ) -> Option<Vec<Self::Slot>>;
+
+    /// Public homomorphic evaluation of the helper
+    fn evaluate(
+        pp: Self::PublicParameters,
+        period: u16,
+        slots: &[Self::Slot],
+    ) -> Self::HelperOutput;
+
+    /// Whether `output` is the evaluation of `slots`, the inputs its digest commits to
+    fn verify_output(
+        pp: Self::PublicParameters,
+        slots: &[Self::Slot],
+        output: &Self::HelperOutput,
+    ) -> bool;
+
+    fn output_period(output: &Self::HelperOutput) -> u16;
+
+    fn output_digest(output: &Self::HelperOutput) -> [u8; 32];
+
+    /// Bytes of the helper output covered by the helper's signature
+    fn output_to_bytes(output: &Self::HelperOutput) -> Vec<u8>;
+
+    fn partial_decrypt(
+        pp: Self::PublicParameters,
+        share: Self::SecretKeyShare,
+        output: &Self::HelperOutput,
+        security: Security,
+    ) -> Self::PartialDecryption;
+
+    fn dlog_table(pp: Self::PublicParameters, bound: u64) -> Self::DlogTable;
+
+    fn vk_share_id(vk: &Self::VerificationKey) -> ShareId;
+
+    /// Share id of a partial decryption, None if it is malformed
+    fn pdec_share_id(pdec: &Self::PartialDecryption) -> Option<ShareId>;
+
+    /// Whether `pdec` is a partial decryption of `output` matching `vk`; proofs are only checked in
+    /// the malicious variant
+    fn verify_partial_decryption(
+        pp: Self::PublicParameters,
+        vk: Self::VerificationKey,
+        output: &Self::HelperOutput,
+        pdec: &Self::PartialDecryption,
+        security: Security,
+    ) -> bool;
+
+    /// Combine `threshold` partial decryptions with distinct share ids, None if decryption fails
+    fn combine(
+        pp: Self::PublicParameters,
+        table: &Self::DlogTable,
+        output: &Self::HelperOutput,
+        pdecs: &[&Self::PartialDecryption],
+        threshold: usize,
+    ) -> Option<Combined<Self>>;
+
+    fn partial_decrypt2(
+        pp: Self::PublicParameters,
+        share: Self::SecretKeyShare,
+        intermediate: &Self::Intermediate,
+        security: Security,
+    ) -> Self::PartialDecryption2;
+
+    fn pdec2_share_id(pdec: &Self::PartialDecryption2) -> Option<ShareId>;
+
+    fn verify_partial_decryption2(
+        pp: Self::PublicParameters,
+        vk: Self::VerificationKey,
+        intermediate: &Self::Intermediate,
+        pdec: &Self::PartialDecryption2,
+        security: Security,
+    ) -> bool;
+
+    /// Combine `threshold` second-round partial decryptions, None if decryption fails
+    fn combine2(
+        pp: Self::PublicParameters,
+        table: &Self::DlogTable,
+        intermediate: &Self::Intermediate,
+        pdecs: &[&Self::PartialDecryption2],
+        threshold: usize,
+    ) -> Option<Self::Plaintext>;
+}
+
+/// Result of combining first-round partial decryptions
+pub enum Combined<S: Protocol + ?Sized> {
+    /// Recipients must partially decrypt the intermediate decryption
+    Round2(S::Intermediate),
+    Done(S::Plaintext),
+}
+
+/// f1: number of recipients who showed up, as a sum of threshold ElGamal encryptions of bits
+pub struct F1<G: Group>(PhantomData<G>);
+
+/// f2: recipient data gated by an indicator bit, as products of threshold BGN encryptions
+pub struct F2<P: Pairing>(PhantomData<P>);
+
+impl<G: Group> Protocol for F1<G> {
+    type PublicParameters = thelgamal::PublicParameters<G>;
+    type PublicKey = thelgamal::PublicKey<G>;
+    type SecretKeyShare = thelgamal::SecretKeyShare<G>;
+    type Slot = thelgamal::Ciphertext<G>;
+    type HelperOutput = thelgamal::HelperOutput<G>;
+    /// A partial decryption with a proof in the malicious variant
+    type PartialDecryption = (
+        thelgamal::PartialDecryption<G>,
+        Option<thelgamal::PartialDecryptionProof<G>>,
+    );
+    type Intermediate = Infallible;
+    type PartialDecryption2 = Infallible;
+    type VerificationKey = thelgamal::VerificationKey<G>;
+    type DlogTable = BabyStepTable<G>;
+    type Plaintext = G::ScalarField;
+
+    fn encode_slots(
+        pp: Self::PublicParameters,
+        pk: Self::PublicKey,
+        period: u16,
+        tags: &[[u8; TAG_BYTELEN]],
+        entitlement: u64,
+        indicator: bool,
+        _data: u64,
+        security: Security,
+    ) -> Option<Vec<Vec<u8>>> {
+        match security {
+            Security::HonestButCurious => Some(
+                (0..tags.len().max(1))
+                    .map(|k| {
+                        let msg = G::ScalarField::from(slot_bit(k, entitlement, indicator) as u64);
+                        thelgamal::encrypt::<G>(pp, pk, msg).to_bytes()
+                    })
+                    .collect(),
+            ),
+            Security::Malicious => {
+                let sub = submission::submit_thelgamal::<G>(
+                    pp,
+                    pk,
+                    period,
+                    first_tag(tags),
+                    entitlement,
+                    &submission_bits(entitlement, indicator),
+                )
+                .ok()?;
+                Some(submission_to_bytes(&sub, |k| sub.slots[k].to_bytes()))
+            }
+        }
+    }
+
+    fn decode_slots(
+        pp: Self::PublicParameters,
+        pk: Self::PublicKey,
+        period: u16,
+        pts: &[Vec<u8>],
+        audit: Option<&Audit>,
+        security: Security,
+    ) -> Option<Vec<Self::Slot>> {
+        let read_ct = |reader: &mut &[u8]| {
+            <(G, G)>::deserialize_compressed(reader)
+                .ok()
+                .map(thelgamal::Ciphertext)
+        };
+        match security {
+            Security::HonestButCurious => pts
+                .iter()
+                .map(|pt| {
+                    let mut reader = &pt[..];
+                    let ct = read_ct(&mut reader)?;
+                    reader.is_empty().then_some(ct)
+                })
+                .collect(),
+            Security::Malicious => {
+                let (sub, _) = submission_from_bytes(period, pts, |reader| {
+                    read_ct(reader).map(|ct| (ct, ()))
+                })?;
+                (audited(&sub, audit) && submission::verify_thelgamal::<G>(pp, pk, period, &sub))
+                    .then_some(sub.slots)
+            }
+        }
+    }
+
+    fn evaluate(
+        _pp: Self::PublicParameters,
+        period: u16,
+        slots: &[Self::Slot],
+    ) -> Self::HelperOutput {
+        thelgamal::evaluate_helper::<G>(period, slots)
+    }
+
+    fn verify_output(
+        _pp: Self::PublicParameters,
+        slots: &[Self::Slot],
+        output: &Self::HelperOutput,
+    ) -> bool {
+        thelgamal::verify_helper_output::<G>(slots, output)
+    }
+
+    fn output_period(output: &Self::HelperOutput) -> u16 {
+        output.period
+    }
+
+    fn output_digest(output: &Self::HelperOutput) -> [u8; 32] {
+        output.inputs_digest
+    }
+
+    fn output_to_bytes(output: &Self::HelperOutput) -> Vec<u8> {
+        output.to_bytes()
+    }
+
+    fn partial_decrypt(
+        pp: Self::PublicParameters,
+        share: Self::SecretKeyShare,
+        output: &Self::HelperOutput,
+        security: Security,
+    ) -> Self::PartialDecryption {
+        match security {
+            Security::HonestButCurious => (
+                thelgamal::partial_decrypt::<G>(pp, share, output.output),
+                None,
+            ),
+            Security::Malicious => {
+                let (pdec, proof) =
+                    thelgamal::partial_decrypt_with_proof::<G>(pp, share, output.output);
+                (pdec, Some(proof))
+            }
+        }
+    }
+
+    fn dlog_table(pp: Self::PublicParameters, bound: u64) -> Self::DlogTable {
+        thelgamal::dlog_table::<G>(pp, bound)
+    }
+
+    fn vk_share_id(vk: &Self::VerificationKey) -> ShareId {
+        vk.0
+    }
+
+    fn pdec_share_id(pdec: &Self::PartialDecryption) -> Option<ShareId> {
+        Some(pdec.0.0)
+    }
+
+    fn verify_partial_decryption(
+        pp: Self::PublicParameters,
+        vk: Self::VerificationKey,
+        output: &Self::HelperOutput,
+        pdec: &Self::PartialDecryption,
+        security: Security,
+    ) -> bool {
+        let (pdec, proof) = pdec;
+        match (security, proof) {
+            (Security::HonestButCurious, _) => pdec.0 == vk.0,
+            (Security::Malicious, Some(proof)) => {
+                thelgamal::verify_partial_decryption::<G>(pp, vk, output.output, *pdec, proof)
+            }
+            (Security::Malicious, None) => false,
+        }
+    }
+
+    fn combine(
+        _pp: Self::PublicParameters,
+        table: &Self::DlogTable,
+        output: &Self::HelperOutput,
+        pdecs: &[&Self::PartialDecryption],
+        threshold: usize,
+    ) -> Option<Combined<Self>> {
+        let pdecs = pdecs.iter().map(|(pdec, _)| *pdec).collect::<Vec<_>>();
+        thelgamal::final_decrypt_with_table::<G>(table, output.output, &pdecs, threshold)
+            .ok()
+            .map(Combined::Done)
+    }
+
+    fn partial_decrypt2(
+        _pp: Self::PublicParameters,
+        _share: Self::SecretKeyShare,
+        intermediate: &Self::Intermediate,
+        _security: Security,
+    ) -> Self::PartialDecryption2 {
+        match *intermediate {}
+    }
+
+    fn pdec2_share_id(pdec: &Self::PartialDecryption2) -> Option<ShareId> {
+        match *pdec {}
+    }
+
+    fn verify_partial_decryption2(
+        _pp: Self::PublicParameters,
+        _vk: Self::VerificationKey,
+        intermediate: &Self::Intermediate,
+        _pdec: &Self::PartialDecryption2,
+        _security: Security,
+    ) -> bool {
+        match *intermediate {}
+    }
+
+    fn combine2(
+        _pp: Self::PublicParameters,
+        _table: &Self::DlogTable,
+        intermediate: &Self::Intermediate,
+        _pdecs: &[&Self::PartialDecryption2],
+        _threshold: usize,
+    ) -> Option<Self::Plaintext> {
+        match *intermediate {}
+    }
+}
+
+impl<P: Pairing> Protocol for F2<P> {
+    type PublicParameters = thbgn::PublicParameters<P>;
+    type PublicKey = thbgn::PublicKey<P>;
+    type SecretKeyShare = thbgn::SecretKeyShare<P>;
+    /// Indicator and data ciphertexts
+    type Slot = Vec<thbgn::Ciphertext1<P>>;
+    type HelperOutput = thbgn::HelperOutput<P>;
+    /// For each output ciphertext, a partial decryption with a proof in the malicious variant
+    type PartialDecryption = Vec<
+        Vec<(
+            thbgn::PartialDecryption<P>,
+            Option<thbgn::PartialDecryptionProof<P>>,
+        )>,
+    >;
+    /// Intermediate decryption of each output ciphertext
+    type Intermediate = Vec<Vec<thbgn::IntermediateDec<P>>>;
+    type PartialDecryption2 = Vec<
+        Vec<(
+            thbgn::PartialDecryption2<P>,
+            Option<thbgn::PartialDecryption2Proof<P>>,
+        )>,
+    >;
+    type VerificationKey = thbgn::VerificationKey<P>;
+    type DlogTable = thbgn::DlogTable<P>;
+    type Plaintext = Vec<Vec<P::ScalarField>>;
+
+    fn encode_slots(
+        pp: Self::PublicParameters,
+        pk: Self::PublicKey,
+        period: u16,
+        tags: &[[u8; TAG_BYTELEN]],
+        entitlement: u64,
+        indicator: bool,
+        data: u64,
+        security: Security,
+    ) -> Option<Vec<Vec<u8>>> {
+        if data >> DATA_BITS != 0 {
+            return None;
+        }
+        let slot_data = |k: usize| if k == 0 { data } else { 0 };
+        match security {
+            Security::HonestButCurious => Some(
+                (0..tags.len().max(1))
+                    .map(|k| {
+                        let msg_bit =
+                            P::ScalarField::from(slot_bit(k, entitlement, indicator) as u64);
+                        let msg_data = P::ScalarField::from(slot_data(k));
+                        let mut bytes = thbgn::encrypt::<P>(pp, pk, msg_bit).to_bytes();
+                        bytes.extend_from_slice(&thbgn::encrypt::<P>(pp, pk, msg_data).to_bytes());
+                        bytes
+                    })
+                    .collect(),
+            ),
+            Security::Malicious => {
+                let tag = first_tag(tags);
+                let sub = submission::submit_thbgn::<P>(
+                    pp,
+                    pk,
+                    period,
+                    tag,
+                    entitlement,
+                    &submission_bits(entitlement, indicator),
+                )
+                .ok()?;
+                let context = data_context(period, &tag);
+                let data_cts = (0..MAX_ENTITLEMENT)
+                    .map(|k| {
+                        thbgn::encrypt_with_range_proof::<P>(
+                            pp,
+                            pk,
+                            slot_data(k),
+                            DATA_BITS,
+                            &context,
+                        )
+                        .ok()
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+                Some(submission_to_bytes(&sub, |k| {
+                    let (ct_data, proof_data) = &data_cts[k];
+                    let mut bytes = sub.slots[k].to_bytes();
+                    bytes.extend_from_slice(&ct_data.to_bytes());
+                    proof_data.serialize_compressed(&mut bytes).unwrap();
+                    bytes
+                }))
+            }
+        }
+    }
+
+    fn decode_slots(
+        pp: Self::PublicParameters,
+        pk: Self::PublicKey,
+        period: u16,
+        pts: &[Vec<u8>],
+        audit: Option<&Audit>,
+        security: Security,
+    ) -> Option<Vec<Self::Slot>> {
+        let read_ct = |reader: &mut &[u8]| {
+            <(P::G1, P::G1, P::G2, P::G2)>::deserialize_compressed(reader)
+                .ok()
+                .map(thbgn::Ciphertext1)
+        };
+        match security {
+            Security::HonestButCurious => pts
+                .iter()
+                .map(|pt| {
+                    let mut reader = &pt[..];
+                    let cts = vec![read_ct(&mut reader)?, read_ct(&mut reader)?];
+                    reader.is_empty().then_some(cts)
+                })
+                .collect(),
+            Security::Malicious => {
+                let (sub, data) = submission_from_bytes(period, pts, |reader| {
+                    let ct_bit = read_ct(reader)?;
+                    let ct_data = read_ct(reader)?;
+                    let proof_data = thbgn::RangeProof::deserialize_compressed(reader).ok()?;
+                    Some((ct_bit, (ct_data, proof_data)))
+                })?;
+                let context = data_context(sub.period, &sub.tag);
+                let valid = audited(&sub, audit)
+                    && submission::verify_thbgn::<P>(pp, pk, period, &sub)
+                    && data.iter().all(|(ct_data, proof_data)| {
+                        thbgn::verify_range::<P>(pp, pk, ct_data, DATA_BITS, proof_data, &context)
+                    });
+                valid.then(|| {
+                    sub.slots
+                        .iter()
+                        .zip(data)
+                        .map(|(ct_bit, (ct_data, _))| vec![*ct_bit, ct_data])
+                        .collect()
+                })
+            }
+        }
+    }
+
+    fn evaluate(
+        pp: Self::PublicParameters,
+        period: u16,
+        slots: &[Self::Slot],
+    ) -> Self::HelperOutput {
+        thbgn::evaluate_helper::<P>(pp, period, slots)
+    }
+
+    fn verify_output(
+        pp: Self::PublicParameters,
+        slots: &[Self::Slot],
+        output: &Self::HelperOutput,
+    ) -> bool {
+        thbgn::verify_helper_output::<P>(pp, slots, output)
+    }
+
+    fn output_period(output: &Self::HelperOutput) -> u16 {
+        output.period
+    }
+
+    fn output_digest(output: &Self::HelperOutput) -> [u8; 32] {
+        output.inputs_digest
+    }
+
+    fn output_to_bytes(output: &Self::HelperOutput) -> Vec<u8> {
+        output.to_bytes()
+    }
+
+    fn partial_decrypt(
+        pp: Self::PublicParameters,
+        share: Self::SecretKeyShare,
+        output: &Self::HelperOutput,
+        security: Security,
+    ) -> Self::PartialDecryption {
+        output
+            .output
+            .iter()
+            .map(|cts| {
+                cts.iter()
+                    .map(|ct| match security {
+                        Security::HonestButCurious => {
+                            (thbgn::partial_decrypt::<P>(pp, share, *ct), None)
+                        }
+                        Security::Malicious => {
+                            let (pdec, proof) =
+                                thbgn::partial_decrypt_with_proof::<P>(pp, share, *ct);
+                            (pdec, Some(proof))
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn dlog_table(pp: Self::PublicParameters, bound: u64) -> Self::DlogTable {
+        thbgn::dlog_table::<P>(pp, bound)
+    }
+
+    fn vk_share_id(vk: &Self::VerificationKey) -> ShareId {
+        vk.0
+    }
+
+    fn pdec_share_id(pdec: &Self::PartialDecryption) -> Option<ShareId> {
+        same_share_id(pdec.iter().flatten().map(|(pdec, _)| pdec.0))
+    }
+
+    fn verify_partial_decryption(
+        pp: Self::PublicParameters,
+        vk: Self::VerificationKey,
+        output: &Self::HelperOutput,
+        pdec: &Self::PartialDecryption,
+        security: Security,
+    ) -> bool {
+        same_shape(&output.output, pdec)
+            && output
+                .output
+                .iter()
+                .flatten()
+                .zip(pdec.iter().flatten())
+                .all(|(ct, (pdec, proof))| match (security, proof) {
+                    (Security::HonestButCurious, _) => pdec.0 == vk.0,
+                    (Security::Malicious, Some(proof)) => {
+                        thbgn::verify_partial_decryption::<P>(pp, vk, *ct, *pdec, proof)
+                    }
+                    (Security::Malicious, None) => false,
+                })
+    }
+
+    fn combine(
+        pp: Self::PublicParameters,
+        _table: &Self::DlogTable,
+        _output: &Self::HelperOutput,
+        pdecs: &[&Self::PartialDecryption],
+        threshold: usize,
+    ) -> Option<Combined<Self>> {
+        let shape = pdecs.first()?;
+        let intermediate = transpose(shape, pdecs)
+            .into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .map(|pdecs| {
+                        let pdecs = pdecs.iter().map(|(pdec, _)| *pdec).collect::<Vec<_>>();
+                        thbgn::intermediate_dec::<P>(pp, &pdecs, threshold).ok()
+                    })
+                    .collect::<Option<Vec<_>>>()
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(Combined::Round2(intermediate))
+    }
+
+    fn partial_decrypt2(
+        pp: Self::PublicParameters,
+        share: Self::SecretKeyShare,
+        intermediate: &Self::Intermediate,
+        security: Security,
+    ) -> Self::PartialDecryption2 {
+        intermediate
+            .iter()
+            .map(|idecs| {
+                idecs
+                    .iter()
+                    .map(|idec| match security {
+                        Security::HonestButCurious => {
+                            (thbgn::partial_decrypt2::<P>(pp, share, *idec), None)
+                        }
+                        Security::Malicious => {
+                            let (pdec, proof) =
+                                thbgn::partial_decrypt2_with_proof::<P>(pp, share, *idec);
+                            (pdec, Some(proof))
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn pdec2_share_id(pdec: &Self::PartialDecryption2) -> Option<ShareId> {
+        same_share_id(pdec.iter().flatten().map(|(pdec, _)| pdec.0))
+    }
+
+    fn verify_partial_decryption2(
+        pp: Self::PublicParameters,
+        vk: Self::VerificationKey,
+        intermediate: &Self::Intermediate,
+        pdec: &Self::PartialDecryption2,
+        security: Security,
+    ) -> bool {
+        same_shape(intermediate, pdec)
+            && intermediate
+                .iter()
+                .flatten()
+                .zip(pdec.iter().flatten())
+                .all(|(idec, (pdec, proof))| match (security, proof) {
+                    (Security::HonestButCurious, _) => pdec.0 == vk.0,
+                    (Security::Malicious, Some(proof)) => {
+                        thbgn::verify_partial_decryption2::<P>(pp, vk, *idec, *pdec, proof)
+                    }
+                    (Security::Malicious, None) => false,
+                })
+    }
+
+    fn combine2(
+        _pp: Self::PublicParameters,
+        table: &Self::DlogTable,
+        _intermediate: &Self::Intermediate,
+        pdecs: &[&Self::PartialDecryption2],
+        threshold: usize,
+    ) -> Option<Self::Plaintext> {
+        let shape = pdecs.first()?;
+        transpose(shape, pdecs)
+            .into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .map(|pdecs| {
+                        let pdecs = pdecs.iter().map(|(pdec, _)| *pdec).collect::<Vec<_>>();
+                        thbgn::final_decrypt_with_table::<P>(table, &pdecs, threshold).ok()
+                    })
+                    .collect::<Option<Vec<_>>>()
+            })
+            .collect()
+    }
+}
+
+/// Tag a malicious submission is bound to, all zeros for a recipient without tags
+fn first_tag(tags: &[[u8; TAG_BYTELEN]]) -> [u8; TAG_BYTELEN] {
+    tags.first().copied().unwrap_or([0u8; TAG_BYTELEN])
+}
+
+/// Context the data range proofs of a malicious f2 submission are bound to, so that they cannot be
+/// replayed by another recipient or in another period
+fn data_context(period: u16, tag: &[u8; TAG_BYTELEN]) -> Vec<u8> {
+    let mut context = period.to_be_bytes().to_vec();
+    context.extend_from_slice(tag);
+    context
+}
+
+/// Whether slot k carries the indicator: only the first `entitlement` slots are real
+fn slot_bit(k: usize, entitlement: u64, indicator: bool) -> bool {
+    indicator && (k as u64) < entitlement
+}
+
+/// Bits of the MAX_ENTITLEMENT slots of a malicious submission
+fn submission_bits(entitlement: u64, indicator: bool) -> [bool; MAX_ENTITLEMENT] {
+    std::array::from_fn(|k| slot_bit(k, entitlement, indicator))
+}
+
+/// Whether a submission is bound to one of the tags the auditor checked and to the entitlement it
+/// registered, if there is an auditor. The proof itself ties the tag and the entitlement to the
+/// slots, which `verify_*` checks once for the whole submission of the recipient.
+fn audited<C, F: PrimeField>(sub: &RecipientSubmission<C, F>, audit: Option<&Audit>) -> bool {
+    audit.is_none_or(|audit| audit.tags.contains(&sub.tag) && sub.entitlement == audit.entitlement)
+}
+
+/// Plaintexts of a submission, one per slot: `body(k)` and the bit proof of slot k. The first one
+/// starts with the tag, the entitlement and the sum proof.
+fn submission_to_bytes<C, F: PrimeField>(
+    sub: &RecipientSubmission<C, F>,
+    body: impl Fn(usize) -> Vec<u8>,
+) -> Vec<Vec<u8>> {
+    (0..sub.slots.len())
+        .map(|k| {
+            let mut bytes = Vec::new();
+            if k == 0 {
+                bytes.extend_from_slice(&sub.tag);
+                bytes.extend_from_slice(&sub.entitlement.to_be_bytes());
+                sub.sum_proof.serialize_compressed(&mut bytes).unwrap();
+            }
+            bytes.extend_from_slice(&body(k));
+            sub.bit_proofs[k].serialize_compressed(&mut bytes).unwrap();
+            bytes
+        })
+        .collect()
+}
+
+/// Inverse of `submission_to_bytes` for a submission of `period`. `body` parses the ciphertext of
+/// a slot and whatever else the slot carries.
+fn submission_from_bytes<C, X, F: PrimeField>(
+    period: u16,
+    pts: &[Vec<u8>],
+    body: impl Fn(&mut &[u8]) -> Option<(C, X)>,
+) -> Option<(RecipientSubmission<C, F>, Vec<X>)> {
+    let (first, _) = pts.split_first()?;
+    let mut reader = &first[..];
+    let tag = reader.get(..TAG_BYTELEN)?.try_into().ok()?;
+    let entitlement = reader.get(TAG_BYTELEN..TAG_BYTELEN + 8)?.try_into().ok()?;
+    reader = &reader[TAG_BYTELEN + 8..];
+    let sum_proof = BitProof::deserialize_compressed(&mut reader).ok()?;
+
+    let mut slots = Vec::with_capacity(pts.len());
+    let mut bit_proofs = Vec::with_capacity(pts.len());
+    let mut extras = Vec::with_capacity(pts.len());
+    for (k, pt) in pts.iter().enumerate() {
+        if k > 0 {
+            reader = &pt[..];
+        }
+        let (slot, extra) = body(&mut reader)?;
+        bit_proofs.push(BitProof::deserialize_compressed(&mut reader).ok()?);
+        if !reader.is_empty() {
+            return None;
+        }
+        slots.push(slot);
+        extras.push(extra);
+    }
+    let submission = RecipientSubmission {
+        period,
+        tag,
+        entitlement: u64::from_be_bytes(entitlement),
+        slots,
+        bit_proofs,
+        sum_proof,
+    };
+    Some((submission, extras))
+}
+
+/// The common share id of `ids`, None if there is none or they differ
+fn same_share_id(mut ids: impl Iterator<Item = ShareId>) -> Option<ShareId> {
+    let id = ids.next()?;
+    ids.all(|other| other == id).then_some(id)
+}
+
+fn same_shape<T, U>(a: &[Vec<T>], b: &[Vec<U>]) -> bool {
+    a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| a.len() == b.len())
+}
+
+/// Group the (j, k) entries of each recipient's matrix in `pdecs`, all shaped like `shape`
+fn transpose<T: Copy, U>(shape: &[Vec<U>], pdecs: &[&Vec<Vec<T>>]) -> Vec<Vec<Vec<T>>> {
+    shape
+        .iter()
+        .enumerate()
+        .map(|(j, row)| {
+            (0..row.len())
+                .map(|k| pdecs.iter().map(|pdec| pdec[j][k]).collect())
+                .collect()
+        })
+        .collect()
+}
@@ -0,0 +1,439 @@
+//! Recipient role of the thHE protocols: encrypt one slot per secret tag for the helper (and the
+//! auditor), then partially decrypt the helper's signed output with a key share.
+use tink_core::keyset;
+
+use crate::protocol::{Protocol, Security};
+use crate::{MAX_ENTITLEMENT, MIN_ENTITLEMENT, TAG_BYTELEN};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RecipientError {
+    /// The data does not fit in DATA_BITS bits
+    DataOutOfRange(u64),
+    /// (expected, received) period of a helper output
+    WrongPeriod(u16, u16),
+    /// The helper output does not carry a valid helper signature
+    InvalidSignature,
+    /// The helper output is not the evaluation of the inputs it was sent with
+    InvalidHelperOutput,
+    /// Encrypting to the helper or the auditor failed
+    Encryption,
+    /// Number of secret tags of a recipient with an auditor, which must be MAX_ENTITLEMENT
+    TagCount(usize),
+    /// The entitlement is not in [MIN_ENTITLEMENT, MAX_ENTITLEMENT]
+    EntitlementOutOfRange(usize),
+}
+
+impl std::fmt::Display for RecipientError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RecipientError::DataOutOfRange(data) => write!(f, "data {} out of range", data),
+            RecipientError::WrongPeriod(expected, period) => {
+                write!(f, "output for period {}, expected {}", period, expected)
+            }
+            RecipientError::InvalidSignature => write!(f, "invalid helper signature"),
+            RecipientError::InvalidHelperOutput => {
+                write!(f, "helper output does not match its inputs")
+            }
+            RecipientError::Encryption => write!(f, "public-key encryption failed"),
+            RecipientError::TagCount(count) => {
+                write!(f, "{} secret tags, {} needed", count, MAX_ENTITLEMENT)
+            }
+            RecipientError::EntitlementOutOfRange(entitlement) => write!(
+                f,
+                "entitlement {} not in [{}, {}]",
+                entitlement, MIN_ENTITLEMENT, MAX_ENTITLEMENT
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RecipientError {}
+
+/// What a recipient sends for a period
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Submission {
+    pub period: u16,
+    /// One ciphertext per slot under the helper's key, with the period as context
+    pub helper_ctxts: Vec<Vec<u8>>,
+    /// secret_tag || entitlement || helper ciphertext of each slot under the auditor's key, empty
+    /// without auditor
+    pub auditor_ctxts: Vec<Vec<u8>>,
+}
+
+/// A recipient's partial decryption of the helper output of a period
+pub struct PartialDecryptionMsg<S: Protocol> {
+    pub period: u16,
+    pub recipient: usize,
+    pub pdec: S::PartialDecryption,
+}
+
+impl<S: Protocol> Clone for PartialDecryptionMsg<S>
+where
+    S::PartialDecryption: Clone,
+{
+    fn clone(&self) -> Self {
+        PartialDecryptionMsg {
+            period: self.period,
+            recipient: self.recipient,
+            pdec: self.pdec.clone(),
+        }
+    }
+}
+
+/// A recipient's partial decryption of the intermediate decryption of a period, for two-round
+/// schemes
+pub struct PartialDecryption2Msg<S: Protocol> {
+    pub period: u16,
+    pub recipient: usize,
+    pub pdec: S::PartialDecryption2,
+}
+
+impl<S: Protocol> Clone for PartialDecryption2Msg<S>
+where
+    S::PartialDecryption2: Clone,
+{
+    fn clone(&self) -> Self {
+        PartialDecryption2Msg {
+            period: self.period,
+            recipient: self.recipient,
+            pdec: self.pdec.clone(),
+        }
+    }
+}
+
+pub struct Recipient<S: Protocol> {
+    id: usize,
+    security: Security,
+    pp: S::PublicParameters,
+    pk: S::PublicKey,
+    share: S::SecretKeyShare,
+    pk_enc_helper: keyset::Handle,
+    vk_sig_helper: keyset::Handle,
+    pk_enc_auditor: Option<keyset::Handle>,
+    entitlement: usize,
+    secret_tags: Vec<[u8; TAG_BYTELEN]>,
+}
+
+impl<S: Protocol> Recipient<S> {
+    /// Recipient with entitlement MIN_ENTITLEMENT submitting directly to the helper
+    pub fn new(
+        id: usize,
+        security: Security,
+        pp: S::PublicParameters,
+        pk: S::PublicKey,
+        share: S::SecretKeyShare,
+        pk_enc_helper: keyset::Handle,
+        vk_sig_helper: keyset::Handle,
+    ) -> Self {
+        tink_hybrid::init();
+        tink_signature::init();
+        Recipient {
+            id,
+            security,
+            pp,
+            pk,
+            share,
+            pk_enc_helper,
+            vk_sig_helper,
+            pk_enc_auditor: None,
+            entitlement: MIN_ENTITLEMENT,
+            secret_tags: Vec::new(),
+        }
+    }
+
+    /// Route submissions through the auditor with the `entitlement` the issuer registered, with
+    /// one slot per secret tag of the period
+    pub fn with_auditor(
+        mut self,
+        pk_enc_auditor: keyset::Handle,
+        entitlement: usize,
+        secret_tags: Vec<[u8; TAG_BYTELEN]>,
+    ) -> Self {
+        self.pk_enc_auditor = Some(pk_enc_auditor);
+        self.entitlement = entitlement;
+        self.secret_tags = secret_tags;
+        self
+    }
+
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn share(&self) -> S::SecretKeyShare {
+        self.share
+    }
+
+    /// Encrypt `indicator` in the first `entitlement` slots, `data` in the first slot and dummy
+    /// zeros elsewhere. A recipient with an auditor needs MAX_ENTITLEMENT secret tags, one per
+    /// slot, so that the number of slots reveals nothing about the entitlement.
+    pub fn submit(
+        &self,
+        period: u16,
+        indicator: bool,
+        data: u64,
+    ) -> Result<Submission, RecipientError> {
+        if !(MIN_ENTITLEMENT..=MAX_ENTITLEMENT).contains(&self.entitlement) {
+            return Err(RecipientError::EntitlementOutOfRange(self.entitlement));
+        }
+        if self.pk_enc_auditor.is_some() && self.secret_tags.len() != MAX_ENTITLEMENT {
+            return Err(RecipientError::TagCount(self.secret_tags.len()));
+        }
+        let enc = tink_hybrid::new_encrypt(&self.pk_enc_helper)
+            .map_err(|_| RecipientError::Encryption)?;
+        let pts = S::encode_slots(
+            self.pp,
+            self.pk,
+            period,
+            &self.secret_tags,
+            self.entitlement as u64,
+            indicator,
+            data,
+            self.security,
+        )
+        .ok_or(RecipientError::DataOutOfRange(data))?;
+        let helper_ctxts = pts
+            .iter()
+            .map(|pt| {
+                enc.encrypt(pt, &period.to_be_bytes())
+                    .map_err(|_| RecipientError::Encryption)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let auditor_ctxts = match &self.pk_enc_auditor {
+            None => Vec::new(),
+            Some(pk_enc_auditor) => {
+                let enc_auditor = tink_hybrid::new_encrypt(pk_enc_auditor)
+                    .map_err(|_| RecipientError::Encryption)?;
+                self.secret_tags
+                    .iter()
+                    .zip(helper_ctxts.iter())
+                    .map(|(tag, ct)| {
+                        let mut bytes = tag.to_vec();
+                        bytes.extend_from_slice(&(self.entitlement as u64).to_be_bytes());
+                        bytes.extend_from_slice(ct);
+                        enc_auditor
+                            .encrypt(&bytes, b"")
+                            .map_err(|_| RecipientError::Encryption)
+                    })
+                    .collect::<Result<Vec<_>, _>>()?
+            }
+        };
+
+        Ok(Submission {
+            period,
+            helper_ctxts,
+            auditor_ctxts,
+        })
+    }
+
+    /// Check the helper's signature on its output for `period`, and that the output is the
+    /// evaluation of `inputs`, then partially decrypt it
+    pub fn partially_decrypt(
+        &self,
+        period: u16,
+        inputs: &[S::Slot],
+        helper_output: &S::HelperOutput,
+        signature: &[u8],
+    ) -> Result<PartialDecryptionMsg<S>, RecipientError> {
+        let output_period = S::output_period(helper_output);
+        if output_period != period {
+            return Err(RecipientError::WrongPeriod(period, output_period));
+        }
+        let v = tink_signature::new_verifier(&self.vk_sig_helper)
+            .map_err(|_| RecipientError::InvalidSignature)?;
+        v.verify(signature, &S::output_to_bytes(helper_output))
+            .map_err(|_| RecipientError::InvalidSignature)?;
+        if !S::verify_output(self.pp, inputs, helper_output) {
+            return Err(RecipientError::InvalidHelperOutput);
+        }
+        Ok(PartialDecryptionMsg {
+            period,
+            recipient: self.id,
+            pdec: S::partial_decrypt(self.pp, self.share, helper_output, self.security),
+        })
+    }
+
+    /// Partially decrypt the intermediate decryption the distribution station sent for `period`
+    pub fn partially_decrypt2(
+        &self,
+        period: u16,
+        intermediate: &S::Intermediate,
+    ) -> PartialDecryption2Msg<S> {
+        PartialDecryption2Msg {
+            period,
+            recipient: self.id,
+            pdec: S::partial_decrypt2(self.pp, self.share, intermediate, self.security),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::protocol::{F1, F2};
+    use crate::test_util::keys;
+    use crate::{DATA_BITS, thbgn, thelgamal};
+    use ark_ec::pairing::Pairing;
+
+    type P = ark_bls12_381::Bls12_381;
+    type G1 = <P as Pairing>::G1;
+    type F = <P as Pairing>::ScalarField;
+
+    #[test]
+    fn test_recipient_f1() {
+        let pp = thelgamal::paramgen::<G1>();
+        let (sk, pk) = thelgamal::keygen::<G1>(pp);
+        let shares = thelgamal::share_sk::<G1>(sk, 2, 3).unwrap();
+        let (sk_enc, sk_sig) = keys();
+        let recipient = Recipient::<F1<G1>>::new(
+            1,
+            Security::Malicious,
+            pp,
+            pk,
+            shares[0],
+            sk_enc.public().unwrap(),
+            sk_sig.public().unwrap(),
+        );
+
+        // A malicious submission always has MAX_ENTITLEMENT slots, only the first one real
+        let submission = recipient.submit(7, true, 0).unwrap();
+        assert_eq!(submission.helper_ctxts.len(), MAX_ENTITLEMENT);
+        assert!(submission.auditor_ctxts.is_empty());
+        let dec = tink_hybrid::new_decrypt(&sk_enc).unwrap();
+        let pts = submission
+            .helper_ctxts
+            .iter()
+            .map(|ct| dec.decrypt(ct, &7u16.to_be_bytes()).unwrap())
+            .collect::<Vec<_>>();
+        let slots = F1::<G1>::decode_slots(pp, pk, 7, &pts, None, Security::Malicious).unwrap();
+        assert!(F1::<G1>::decode_slots(pp, pk, 8, &pts, None, Security::Malicious).is_none());
+        assert!(F1::<G1>::decode_slots(pp, pk, 7, &pts[1..], None, Security::Malicious).is_none());
+        let bits = slots
+            .iter()
+            .map(|ct| thelgamal::decrypt::<G1>(pp, sk, *ct, 2).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(bits[0], F::from(1u64));
+        assert!(bits[1..].iter().all(|bit| *bit == F::from(0u64)));
+        let ct = slots[0];
+
+        let output = thelgamal::evaluate_helper::<G1>(7, &[ct]);
+        let signer = tink_signature::new_signer(&sk_sig).unwrap();
+        let signature = signer.sign(&output.to_bytes()).unwrap();
+        let msg = recipient
+            .partially_decrypt(7, &[ct], &output, &signature)
+            .unwrap();
+        let (pdec, proof) = msg.pdec;
+        assert_eq!(pdec, thelgamal::partial_decrypt::<G1>(pp, shares[0], ct));
+        let vk = thelgamal::verification_key::<G1>(pp, shares[0]);
+        assert!(thelgamal::verify_partial_decryption::<G1>(
+            pp,
+            vk,
+            ct,
+            pdec,
+            &proof.unwrap()
+        ));
+
+        assert_eq!(
+            recipient
+                .partially_decrypt(8, &[ct], &output, &signature)
+                .err(),
+            Some(RecipientError::WrongPeriod(8, 7))
+        );
+        let other = thelgamal::evaluate_helper::<G1>(7, &[ct, ct]);
+        assert_eq!(
+            recipient
+                .partially_decrypt(7, &[ct, ct], &other, &signature)
+                .err(),
+            Some(RecipientError::InvalidSignature)
+        );
+        // A signed output is only decrypted along with the inputs it commits to
+        assert_eq!(
+            recipient
+                .partially_decrypt(7, &[ct, ct], &output, &signature)
+                .err(),
+            Some(RecipientError::InvalidHelperOutput)
+        );
+        assert_eq!(
+            recipient
+                .partially_decrypt(7, &[slots[1]], &output, &signature)
+                .err(),
+            Some(RecipientError::InvalidHelperOutput)
+        );
+    }
+
+    #[test]
+    fn test_recipient_f2() {
+        let pp = thbgn::paramgen::<P>();
+        let (sk, pk) = thbgn::keygen::<P>(pp);
+        let shares = thbgn::share_sk::<P>(sk, 2, 3).unwrap();
+        let (sk_enc, sk_sig) = keys();
+        let (sk_enc_auditor, _) = keys();
+        let tags = (1..=MAX_ENTITLEMENT as u8)
+            .map(|i| [i; TAG_BYTELEN])
+            .collect::<Vec<_>>();
+        let new_recipient = || {
+            Recipient::<F2<P>>::new(
+                2,
+                Security::HonestButCurious,
+                pp,
+                pk,
+                shares[1],
+                sk_enc.public().unwrap(),
+                sk_sig.public().unwrap(),
+            )
+        };
+        let recipient =
+            new_recipient().with_auditor(sk_enc_auditor.public().unwrap(), 2, tags.clone());
+
+        // One slot per tag whatever the entitlement, which the auditor reads with each tag
+        let submission = recipient.submit(3, true, 5).unwrap();
+        assert_eq!(submission.helper_ctxts.len(), MAX_ENTITLEMENT);
+        let dec = tink_hybrid::new_decrypt(&sk_enc_auditor).unwrap();
+        for ((ct, tag), helper_ct) in submission
+            .auditor_ctxts
+            .iter()
+            .zip(tags.iter())
+            .zip(submission.helper_ctxts.iter())
+        {
+            let pt = dec.decrypt(ct, b"").unwrap();
+            assert_eq!(&pt[..TAG_BYTELEN], tag);
+            assert_eq!(&pt[TAG_BYTELEN..TAG_BYTELEN + 8], &2u64.to_be_bytes());
+            assert_eq!(&pt[TAG_BYTELEN + 8..], &helper_ct[..]);
+        }
+        assert_eq!(
+            recipient.submit(3, true, 1 << DATA_BITS).unwrap_err(),
+            RecipientError::DataOutOfRange(1 << DATA_BITS)
+        );
+        assert_eq!(
+            new_recipient()
+                .with_auditor(sk_enc_auditor.public().unwrap(), 2, tags[..3].to_vec())
+                .submit(3, true, 5)
+                .unwrap_err(),
+            RecipientError::TagCount(3)
+        );
+        assert_eq!(
+            new_recipient()
+                .with_auditor(sk_enc_auditor.public().unwrap(), 0, tags.clone())
+                .submit(3, true, 5)
+                .unwrap_err(),
+            RecipientError::EntitlementOutOfRange(0)
+        );
+
+        let ct = thbgn::encrypt::<P>(pp, pk, F::from(1u64));
+        let output = thbgn::evaluate_helper::<P>(pp, 3, &[vec![ct, ct]]);
+        let signer = tink_signature::new_signer(&sk_sig).unwrap();
+        let signature = signer.sign(&output.to_bytes()).unwrap();
+        let msg = recipient
+            .partially_decrypt(3, &[vec![ct, ct]], &output, &signature)
+            .unwrap();
+        assert_eq!(msg.recipient, 2);
+        assert_eq!(msg.pdec.len(), 1);
+        assert_eq!(
+            msg.pdec[0][0],
+            (
+                thbgn::partial_decrypt::<P>(pp, shares[1], output.output[0][0]),
+                None
+            )
+        );
+    }
+}
@@ -0,0 +1,416 @@
+//! Distribution station role of the thHE protocols: collect partial decryptions of the helper
+//! output as recipients show up, run the second round for two-round schemes and output the
+//! assessment result once enough shares are in.
+use std::collections::BTreeMap;
+
+use secret_sharing_and_dkg::common::ShareId;
+
+use crate::protocol::{Combined, Protocol, Security};
+use crate::recipient::{PartialDecryption2Msg, PartialDecryptionMsg};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StationError {
+    /// (expected, received) period of a partial decryption
+    WrongPeriod(u16, u16),
+    /// No verification key for this share id
+    UnknownShareId(ShareId),
+    /// Malformed partial decryption, or one whose proof does not verify
+    InvalidPartialDecryption(Option<ShareId>),
+    /// A second-round partial decryption arrived before the first round completed
+    NotInSecondRound,
+    /// Threshold many partial decryptions do not decrypt within the dlog bound
+    Decryption,
+}
+
+impl std::fmt::Display for StationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StationError::WrongPeriod(expected, period) => {
+                write!(
+                    f,
+                    "partial decryption for period {}, expected {}",
+                    period, expected
+                )
+            }
+            StationError::UnknownShareId(id) => write!(f, "unknown share id {}", id),
+            StationError::InvalidPartialDecryption(Some(id)) => {
+                write!(f, "invalid partial decryption from share {}", id)
+            }
+            StationError::InvalidPartialDecryption(None) => {
+                write!(f, "malformed partial decryption")
+            }
+            StationError::NotInSecondRound => write!(f, "first decryption round not complete"),
+            StationError::Decryption => write!(f, "decryption failed"),
+        }
+    }
+}
+
+impl std::error::Error for StationError {}
+
+pub struct DistributionStation<S: Protocol> {
+    security: Security,
+    pp: S::PublicParameters,
+    table: S::DlogTable,
+    vks: BTreeMap<ShareId, S::VerificationKey>,
+    threshold: usize,
+    output: S::HelperOutput,
+    pdecs: BTreeMap<ShareId, S::PartialDecryption>,
+    intermediate: Option<S::Intermediate>,
+    pdecs2: BTreeMap<ShareId, S::PartialDecryption2>,
+    result: Option<S::Plaintext>,
+}
+
+impl<S: Protocol> DistributionStation<S> {
+    /// Station decrypting `output` with `threshold` of the shares behind `vks`
+    pub fn new(
+        security: Security,
+        pp: S::PublicParameters,
+        table: S::DlogTable,
+        vks: &[S::VerificationKey],
+        threshold: usize,
+        output: S::HelperOutput,
+    ) -> Self {
+        DistributionStation {
+            security,
+            pp,
+            table,
+            vks: vks.iter().map(|vk| (S::vk_share_id(vk), *vk)).collect(),
+            threshold,
+            output,
+            pdecs: BTreeMap::new(),
+            intermediate: None,
+            pdecs2: BTreeMap::new(),
+            result: None,
+        }
+    }
+
+    pub fn period(&self) -> u16 {
+        S::output_period(&self.output)
+    }
+
+    /// Intermediate decryption to send to recipients, once the first round of a two-round scheme
+    /// is complete
+    pub fn intermediate(&self) -> Option<&S::Intermediate> {
+        self.intermediate.as_ref()
+    }
+
+    pub fn result(&self) -> Option<&S::Plaintext> {
+        self.result.as_ref()
+    }
+
+    fn received(&self) -> Vec<ShareId> {
+        match self.intermediate {
+            None => self.pdecs.keys().copied().collect(),
+            Some(_) => self.pdecs2.keys().copied().collect(),
+        }
+    }
+
+    /// Number of partial decryptions still needed in the current round, 0 once done
+    pub fn needed(&self) -> usize {
+        match self.result {
+            Some(_) => 0,
+            None => self.threshold.saturating_sub(self.received().len()),
+        }
+    }
+
+    /// Share ids not yet received in the current round, empty once done
+    pub fn missing(&self) -> Vec<ShareId> {
+        if self.result.is_some() {
+            return Vec::new();
+        }
+        let received = self.received();
+        self.vks
+            .keys()
+            .filter(|id| !received.contains(id))
+            .copied()
+            .collect()
+    }
+
+    fn check_period(&self, period: u16) -> Result<(), StationError> {
+        match self.period() {
+            expected if expected == period => Ok(()),
+            expected => Err(StationError::WrongPeriod(expected, period)),
+        }
+    }
+
+    fn vk(&self, id: Option<ShareId>) -> Result<S::VerificationKey, StationError> {
+        let id = id.ok_or(StationError::InvalidPartialDecryption(None))?;
+        self.vks
+            .get(&id)
+            .copied()
+            .ok_or(StationError::UnknownShareId(id))
+    }
+
+    /// Add a first-round partial decryption. Duplicates and partial decryptions arriving after
+    /// the round completed are ignored. A partial decryption that completes the threshold but
+    /// does not combine is dropped, so that another share can take its place; in the malicious
+    /// variant the proofs already rule this out.
+    pub fn add_partial_decryption(
+        &mut self,
+        msg: PartialDecryptionMsg<S>,
+    ) -> Result<(), StationError> {
+        self.check_period(msg.period)?;
+        let id = S::pdec_share_id(&msg.pdec);
+        let vk = self.vk(id)?;
+        let id = S::vk_share_id(&vk);
+        if self.pdecs.contains_key(&id) || self.intermediate.is_some() || self.result.is_some() {
+            return Ok(());
+        }
+        if !S::verify_partial_decryption(self.pp, vk, &self.output, &msg.pdec, self.security) {
+            return Err(StationError::InvalidPartialDecryption(Some(id)));
+        }
+        self.pdecs.insert(id, msg.pdec);
+        if self.pdecs.len() < self.threshold {
+            return Ok(());
+        }
+
+        let pdecs = self.pdecs.values().collect::<Vec<_>>();
+        match S::combine(self.pp, &self.table, &self.output, &pdecs, self.threshold) {
+            Some(Combined::Round2(intermediate)) => self.intermediate = Some(intermediate),
+            Some(Combined::Done(result)) => self.result = Some(result),
+            None => {
+                self.pdecs.remove(&id);
+                return Err(StationError::Decryption);
+            }
+        }
+        Ok(())
+    }
+
+    /// Add a second-round partial decryption. Duplicates and partial decryptions arriving after
+    /// the result is known are ignored, and one that makes combining fail is dropped.
+    pub fn add_partial_decryption2(
+        &mut self,
+        msg: PartialDecryption2Msg<S>,
+    ) -> Result<(), StationError> {
+        self.check_period(msg.period)?;
+        let Some(intermediate) = &self.intermediate else {
+            return Err(StationError::NotInSecondRound);
+        };
+        let vk = self.vk(S::pdec2_share_id(&msg.pdec))?;
+        let id = S::vk_share_id(&vk);
+        if self.pdecs2.contains_key(&id) || self.result.is_some() {
+            return Ok(());
+        }
+        if !S::verify_partial_decryption2(self.pp, vk, intermediate, &msg.pdec, self.security) {
+            return Err(StationError::InvalidPartialDecryption(Some(id)));
+        }
+        self.pdecs2.insert(id, msg.pdec);
+        if self.pdecs2.len() < self.threshold {
+            return Ok(());
+        }
+
+        let pdecs = self.pdecs2.values().collect::<Vec<_>>();
+        match S::combine2(self.pp, &self.table, intermediate, &pdecs, self.threshold) {
+            Some(result) => self.result = Some(result),
+            None => {
+                self.pdecs2.remove(&id);
+                return Err(StationError::Decryption);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::protocol::{F1, F2};
+    use crate::recipient::Recipient;
+    use crate::test_util::keys;
+    use crate::{thbgn, thelgamal};
+    use ark_ec::pairing::Pairing;
+
+    type P = ark_bls12_381::Bls12_381;
+    type G1 = <P as Pairing>::G1;
+    type F = <P as Pairing>::ScalarField;
+
+    #[test]
+    fn test_station_f1() {
+        let pp = thelgamal::paramgen::<G1>();
+        let (sk, pk) = thelgamal::keygen::<G1>(pp);
+        let shares = thelgamal::share_sk::<G1>(sk, 3, 5).unwrap();
+        let cts = [1u64, 0, 1, 1].map(|b| thelgamal::encrypt::<G1>(pp, pk, F::from(b)));
+        let output = thelgamal::evaluate_helper::<G1>(2, &cts);
+        let (sk_enc, sk_sig) = keys();
+        let signature = tink_signature::new_signer(&sk_sig)
+            .unwrap()
+            .sign(&output.to_bytes())
+            .unwrap();
+        let recipients = shares
+            .iter()
+            .enumerate()
+            .map(|(i, share)| {
+                Recipient::<F1<G1>>::new(
+                    i,
+                    Security::Malicious,
+                    pp,
+                    pk,
+                    *share,
+                    sk_enc.public().unwrap(),
+                    sk_sig.public().unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+        let pdec = |i: usize| {
+            recipients[i]
+                .partially_decrypt(2, &cts, &output, &signature)
+                .unwrap()
+        };
+
+        let vks = shares
+            .iter()
+            .map(|s| thelgamal::verification_key::<G1>(pp, *s))
+            .collect::<Vec<_>>();
+        let table = F1::<G1>::dlog_table(pp, 8);
+        let mut station =
+            DistributionStation::<F1<G1>>::new(Security::Malicious, pp, table, &vks, 3, output);
+        assert_eq!(station.needed(), 3);
+
+        // A share of another key under a valid share id is rejected
+        let (other_sk, _) = thelgamal::keygen::<G1>(pp);
+        let forged = thelgamal::share_sk::<G1>(other_sk, 3, 5).unwrap();
+        let mut msg = pdec(0);
+        msg.pdec = F1::<G1>::partial_decrypt(pp, forged[0], &output, Security::Malicious);
+        assert_eq!(
+            station.add_partial_decryption(msg).unwrap_err(),
+            StationError::InvalidPartialDecryption(Some(shares[0].0))
+        );
+        let mut msg = pdec(0);
+        msg.pdec.1 = None;
+        assert_eq!(
+            station.add_partial_decryption(msg).unwrap_err(),
+            StationError::InvalidPartialDecryption(Some(shares[0].0))
+        );
+        station.add_partial_decryption(pdec(4)).unwrap();
+        station.add_partial_decryption(pdec(1)).unwrap();
+        // Duplicates do not count
+        station.add_partial_decryption(pdec(4)).unwrap();
+        assert_eq!(station.needed(), 1);
+        assert_eq!(station.missing(), vec![1, 3, 4]);
+        assert!(station.result().is_none());
+
+        let mut wrong_period = pdec(0);
+        wrong_period.period = 3;
+        assert_eq!(
+            station.add_partial_decryption(wrong_period).unwrap_err(),
+            StationError::WrongPeriod(2, 3)
+        );
+        station.add_partial_decryption(pdec(2)).unwrap();
+        assert!(station.intermediate().is_none());
+        assert_eq!(station.result(), Some(&F::from(3u64)));
+        assert_eq!(station.needed(), 0);
+        // Shares beyond the threshold are accepted
+        station.add_partial_decryption(pdec(0)).unwrap();
+        assert_eq!(station.result(), Some(&F::from(3u64)));
+
+        // Without proofs, a bad partial decryption that makes combining fail is not kept
+        let vks = shares
+            .iter()
+            .map(|s| thelgamal::verification_key::<G1>(pp, *s))
+            .collect::<Vec<_>>();
+        let table = F1::<G1>::dlog_table(pp, 8);
+        let mut station = DistributionStation::<F1<G1>>::new(
+            Security::HonestButCurious,
+            pp,
+            table,
+            &vks,
+            3,
+            output,
+        );
+        let pdec = |i: usize| PartialDecryptionMsg::<F1<G1>> {
+            period: 2,
+            recipient: i,
+            pdec: F1::<G1>::partial_decrypt(pp, shares[i], &output, Security::HonestButCurious),
+        };
+        station.add_partial_decryption(pdec(0)).unwrap();
+        station.add_partial_decryption(pdec(1)).unwrap();
+        let mut bad = pdec(2);
+        bad.pdec.0.1 += pp * F::from(1u64 << 40);
+        assert_eq!(
+            station.add_partial_decryption(bad).unwrap_err(),
+            StationError::Decryption
+        );
+        assert_eq!(station.needed(), 1);
+        station.add_partial_decryption(pdec(2)).unwrap();
+        assert_eq!(station.result(), Some(&F::from(3u64)));
+    }
+
+    #[test]
+    fn test_station_f2() {
+        let pp = thbgn::paramgen::<P>();
+        let (sk, pk) = thbgn::keygen::<P>(pp);
+        let (shares, commitments) = thbgn::share_sk_verifiable::<P>(pp, sk, 2, 3).unwrap();
+        let vks = shares
+            .iter()
+            .map(|s| thbgn::verification_key::<P>(&commitments, s.0))
+            .collect::<Vec<_>>();
+        let inputs = [vec![
+            thbgn::encrypt::<P>(pp, pk, F::from(1u64)),
+            thbgn::encrypt::<P>(pp, pk, F::from(5u64)),
+        ]];
+        let output = thbgn::evaluate_helper::<P>(pp, 4, &inputs);
+        let (sk_enc, sk_sig) = keys();
+        let signature = tink_signature::new_signer(&sk_sig)
+            .unwrap()
+            .sign(&output.to_bytes())
+            .unwrap();
+        let recipient = |i: usize, share: thbgn::SecretKeyShare<P>| {
+            Recipient::<F2<P>>::new(
+                i,
+                Security::Malicious,
+                pp,
+                pk,
+                share,
+                sk_enc.public().unwrap(),
+                sk_sig.public().unwrap(),
+            )
+        };
+        let recipients = shares
+            .iter()
+            .enumerate()
+            .map(|(i, share)| recipient(i, *share))
+            .collect::<Vec<_>>();
+
+        let table = F2::<P>::dlog_table(pp, 8);
+        let mut station = DistributionStation::<F2<P>>::new(
+            Security::Malicious,
+            pp,
+            table,
+            &vks,
+            2,
+            output.clone(),
+        );
+        assert!(matches!(
+            station.add_partial_decryption2(recipients[0].partially_decrypt2(4, &vec![])),
+            Err(StationError::NotInSecondRound)
+        ));
+
+        // A share of another key under a valid share id is rejected
+        let (other_sk, _) = thbgn::keygen::<P>(pp);
+        let (forged, _) = thbgn::share_sk_verifiable::<P>(pp, other_sk, 2, 3).unwrap();
+        let msg = recipient(0, forged[0])
+            .partially_decrypt(4, &inputs, &output, &signature)
+            .unwrap();
+        assert_eq!(
+            station.add_partial_decryption(msg).unwrap_err(),
+            StationError::InvalidPartialDecryption(Some(shares[0].0))
+        );
+
+        for i in [2, 0] {
+            let msg = recipients[i]
+                .partially_decrypt(4, &inputs, &output, &signature)
+                .unwrap();
+            station.add_partial_decryption(msg).unwrap();
+        }
+        let intermediate = station.intermediate().unwrap().clone();
+        assert!(station.result().is_none());
+        assert_eq!(station.missing(), vec![1, 2, 3]);
+
+        for i in [1, 1, 0] {
+            let msg = recipients[i].partially_decrypt2(4, &intermediate);
+            station.add_partial_decryption2(msg).unwrap();
+        }
+        assert_eq!(station.result(), Some(&vec![vec![F::from(5u64)]]));
+        assert!(station.missing().is_empty());
+    }
+}
@@ -0,0 +1,393 @@
+//! Recipient submissions: one encrypted bit per slot, MAX_ENTITLEMENT slots, with a proof bound to
+//! the period, the recipient's tag and their entitlement that every slot is a bit and that the
+//! slots add up to 0 or the entitlement: a recipient takes part with their whole entitlement or not
+//! at all.
+use ark_ec::Group;
+use ark_ec::pairing::Pairing;
+use ark_ff::{Field, PrimeField};
+use ark_std::UniformRand;
+use rand::thread_rng;
+
+use crate::proofs::{BitProof, ElGamalInstance, prove_bit, verify_bit};
+use crate::{MAX_ENTITLEMENT, MIN_ENTITLEMENT, TAG_BYTELEN};
+use crate::{thbgn, thelgamal};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SubmissionError {
+    /// Number of slots, which must be MAX_ENTITLEMENT
+    SlotCount(usize),
+    /// The entitlement is not in [MIN_ENTITLEMENT, MAX_ENTITLEMENT]
+    EntitlementOutOfRange(u64),
+    /// Number of slots set, which must be 0 or the entitlement
+    InvalidSum(usize),
+}
+
+impl std::fmt::Display for SubmissionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SubmissionError::SlotCount(count) => {
+                write!(f, "{} slots, {} needed", count, MAX_ENTITLEMENT)
+            }
+            SubmissionError::EntitlementOutOfRange(entitlement) => write!(
+                f,
+                "entitlement {} not in [{}, {}]",
+                entitlement, MIN_ENTITLEMENT, MAX_ENTITLEMENT
+            ),
+            SubmissionError::InvalidSum(sum) => {
+                write!(f, "{} slots set, neither 0 nor the entitlement", sum)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SubmissionError {}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RecipientSubmission<C, F: PrimeField> {
+    pub period: u16,
+    pub tag: [u8; TAG_BYTELEN],
+    /// Entitlement of the recipient, in [MIN_ENTITLEMENT, MAX_ENTITLEMENT]
+    pub entitlement: u64,
+    pub slots: Vec<C>,
+    /// One proof per slot that it encrypts 0 or 1
+    pub bit_proofs: Vec<BitProof<F>>,
+    /// Proof that the sum of the slots, divided by `entitlement`, encrypts 0 or 1
+    pub sum_proof: BitProof<F>,
+}
+
+/// Submission of exponential ElGamal slots, for f1
+pub type ElGamalSubmission<G: Group> =
+    RecipientSubmission<thelgamal::Ciphertext<G>, G::ScalarField>;
+/// Submission of BGN slots, for f2
+pub type BgnSubmission<P: Pairing> = RecipientSubmission<thbgn::Ciphertext1<P>, P::ScalarField>;
+
+fn label(scheme: &[u8], period: u16, tag: &[u8; TAG_BYTELEN], entitlement: u64) -> Vec<u8> {
+    let mut label = scheme.to_vec();
+    label.extend_from_slice(&period.to_be_bytes());
+    label.extend_from_slice(tag);
+    label.extend_from_slice(&entitlement.to_be_bytes());
+    label
+}
+
+/// Check the arguments of a submission, and return the bit its sum proof is about
+fn check_bits(entitlement: u64, bits: &[bool]) -> Result<bool, SubmissionError> {
+    if bits.len() != MAX_ENTITLEMENT {
+        return Err(SubmissionError::SlotCount(bits.len()));
+    }
+    if !(MIN_ENTITLEMENT as u64..=MAX_ENTITLEMENT as u64).contains(&entitlement) {
+        return Err(SubmissionError::EntitlementOutOfRange(entitlement));
+    }
+    match bits.iter().filter(|b| **b).count() {
+        0 => Ok(false),
+        sum if sum as u64 == entitlement => Ok(true),
+        sum => Err(SubmissionError::InvalidSum(sum)),
+    }
+}
+
+/// Instance of the sum of ElGamal ciphertexts scaled by 1 / entitlement, with randomness
+/// sum r / entitlement, which encrypts a bit if and only if the sum encrypts 0 or `entitlement`
+fn sum_instance<G: Group>(
+    g: G,
+    h: G,
+    cts: impl Iterator<Item = (G, G)>,
+    entitlement: u64,
+) -> ElGamalInstance<G> {
+    let inv = inverse::<G::ScalarField>(entitlement);
+    let (c1, c2) = cts.fold((G::zero(), G::zero()), |(a1, a2), (c1, c2)| {
+        (a1 + c1, a2 + c2)
+    });
+    (g, h, c1 * inv, c2 * inv)
+}
+
+/// 1 / entitlement, for an entitlement already checked to be non-zero
+fn inverse<F: Field>(entitlement: u64) -> F {
+    F::from(entitlement).inverse().unwrap()
+}
+
+const ELGAMAL_LABEL: &[u8] = b"thelgamal submission";
+
+/// Encrypt one bit per slot for `period` and prove the submission well-formed. There must be
+/// MAX_ENTITLEMENT bits, either none or `entitlement` of them set.
+pub fn submit_thelgamal<G: Group>(
+    pp: thelgamal::PublicParameters<G>,
+    pk: thelgamal::PublicKey<G>,
+    period: u16,
+    tag: [u8; TAG_BYTELEN],
+    entitlement: u64,
+    bits: &[bool],
+) -> Result<ElGamalSubmission<G>, SubmissionError> {
+    let taking_part = check_bits(entitlement, bits)?;
+    let mut rng = thread_rng();
+    let label = label(ELGAMAL_LABEL, period, &tag, entitlement);
+    let rs = bits
+        .iter()
+        .map(|_| G::ScalarField::rand(&mut rng))
+        .collect::<Vec<_>>();
+    let slots = bits
+        .iter()
+        .zip(rs.iter())
+        .map(|(b, r)| {
+            thelgamal::encrypt_with_randomness(pp, pk, G::ScalarField::from(*b as u64), *r)
+        })
+        .collect::<Vec<_>>();
+    let bit_proofs = slots
+        .iter()
+        .zip(bits.iter().zip(rs.iter()))
+        .map(|(ct, (b, r))| {
+            prove_bit::<G, G>(
+                &label,
+                &[thelgamal::bit_instance(pp, pk, ct)],
+                &[],
+                *b,
+                &[*r],
+            )
+        })
+        .collect();
+    let instance = sum_instance(pp, pk, slots.iter().map(|ct| ct.0), entitlement);
+    let r = rs.iter().sum::<G::ScalarField>() * inverse::<G::ScalarField>(entitlement);
+    let sum_proof = prove_bit::<G, G>(&label, &[instance], &[], taking_part, &[r]);
+    Ok(RecipientSubmission {
+        period,
+        tag,
+        entitlement,
+        slots,
+        bit_proofs,
+        sum_proof,
+    })
+}
+
+/// Whether a submission for `period` has MAX_ENTITLEMENT slots, so that its length reveals nothing
+/// about the entitlement, and an entitlement in [MIN_ENTITLEMENT, MAX_ENTITLEMENT]
+fn well_shaped<C, F: PrimeField>(period: u16, s: &RecipientSubmission<C, F>) -> bool {
+    s.period == period
+        && s.slots.len() == MAX_ENTITLEMENT
+        && s.bit_proofs.len() == MAX_ENTITLEMENT
+        && (MIN_ENTITLEMENT as u64..=MAX_ENTITLEMENT as u64).contains(&s.entitlement)
+}
+
+/// Check a submission for `period` without decrypting it: every slot is a bit, and the slots add
+/// up to 0 or the entitlement
+pub fn verify_thelgamal<G: Group>(
+    pp: thelgamal::PublicParameters<G>,
+    pk: thelgamal::PublicKey<G>,
+    period: u16,
+    submission: &ElGamalSubmission<G>,
+) -> bool {
+    let s = submission;
+    if !well_shaped(period, s) {
+        return false;
+    }
+    let label = label(ELGAMAL_LABEL, s.period, &s.tag, s.entitlement);
+    let bits_ok = s.slots.iter().zip(s.bit_proofs.iter()).all(|(ct, proof)| {
+        verify_bit::<G, G>(&label, &[thelgamal::bit_instance(pp, pk, ct)], &[], proof)
+    });
+    let instance = sum_instance(pp, pk, s.slots.iter().map(|ct| ct.0), s.entitlement);
+    bits_ok && verify_bit::<G, G>(&label, &[instance], &[], &s.sum_proof)
+}
+
+const BGN_LABEL: &[u8] = b"thbgn submission";
+
+/// Encrypt one bit per slot for `period` and prove the submission well-formed, see
+/// `submit_thelgamal`. The bit proofs also show that both halves of each slot encrypt the same
+/// bit.
+pub fn submit_thbgn<P: Pairing>(
+    pp: thbgn::PublicParameters<P>,
+    pk: thbgn::PublicKey<P>,
+    period: u16,
+    tag: [u8; TAG_BYTELEN],
+    entitlement: u64,
+    bits: &[bool],
+) -> Result<BgnSubmission<P>, SubmissionError> {
+    let taking_part = check_bits(entitlement, bits)?;
+    let mut rng = thread_rng();
+    let label = label(BGN_LABEL, period, &tag, entitlement);
+    let rs = bits
+        .iter()
+        .map(|_| {
+            (
+                P::ScalarField::rand(&mut rng),
+                P::ScalarField::rand(&mut rng),
+            )
+        })
+        .collect::<Vec<_>>();
+    let slots = bits
+        .iter()
+        .zip(rs.iter())
+        .map(|(b, (rho, sigma))| {
+            let msg = P::ScalarField::from(*b as u64);
+            thbgn::encrypt_with_randomness(pp, pk, msg, *rho, *sigma)
+        })
+        .collect::<Vec<_>>();
+    let bit_proofs = slots
+        .iter()
+        .zip(bits.iter().zip(rs.iter()))
+        .map(|(ct, (b, (rho, sigma)))| {
+            let (instance1, instance2) = thbgn::elgamal_instances(pp, pk, ct);
+            prove_bit(&label, &instance1, &instance2, *b, &[*rho, *sigma])
+        })
+        .collect();
+    let ((g1, g2), (h1, h2)) = (pp, pk);
+    let instance1 = sum_instance(g1, h1, slots.iter().map(|ct| (ct.0.0, ct.0.1)), entitlement);
+    let instance2 = sum_instance(g2, h2, slots.iter().map(|ct| (ct.0.2, ct.0.3)), entitlement);
+    let inv = inverse::<P::ScalarField>(entitlement);
+    let rho = rs.iter().map(|r| r.0).sum::<P::ScalarField>() * inv;
+    let sigma = rs.iter().map(|r| r.1).sum::<P::ScalarField>() * inv;
+    let sum_proof = prove_bit(
+        &label,
+        &[instance1],
+        &[instance2],
+        taking_part,
+        &[rho, sigma],
+    );
+    Ok(RecipientSubmission {
+        period,
+        tag,
+        entitlement,
+        slots,
+        bit_proofs,
+        sum_proof,
+    })
+}
+
+/// Check a submission for `period` without decrypting it, see `verify_thelgamal`
+pub fn verify_thbgn<P: Pairing>(
+    pp: thbgn::PublicParameters<P>,
+    pk: thbgn::PublicKey<P>,
+    period: u16,
+    submission: &BgnSubmission<P>,
+) -> bool {
+    let s = submission;
+    if !well_shaped(period, s) {
+        return false;
+    }
+    let label = label(BGN_LABEL, s.period, &s.tag, s.entitlement);
+    let bits_ok = s.slots.iter().zip(s.bit_proofs.iter()).all(|(ct, proof)| {
+        let (a, b) = thbgn::elgamal_instances(pp, pk, ct);
+        verify_bit(&label, &a, &b, proof)
+    });
+    let ((g1, g2), (h1, h2)) = (pp, pk);
+    let instance1 = sum_instance(
+        g1,
+        h1,
+        s.slots.iter().map(|ct| (ct.0.0, ct.0.1)),
+        s.entitlement,
+    );
+    let instance2 = sum_instance(
+        g2,
+        h2,
+        s.slots.iter().map(|ct| (ct.0.2, ct.0.3)),
+        s.entitlement,
+    );
+    bits_ok && verify_bit(&label, &[instance1], &[instance2], &s.sum_proof)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    type P = ark_bls12_381::Bls12_381;
+    type G1 = <P as Pairing>::G1;
+    type F = <P as Pairing>::ScalarField;
+
+    #[test]
+    fn test_thelgamal_submission() {
+        let pp = thelgamal::paramgen::<G1>();
+        let (sk, pk) = thelgamal::keygen::<G1>(pp);
+        let tag = [7u8; TAG_BYTELEN];
+        let mut bits = [false; MAX_ENTITLEMENT];
+        bits[0] = true;
+        bits[3] = true;
+
+        let sub = submit_thelgamal::<G1>(pp, pk, 4, tag, 2, &bits).unwrap();
+        assert_eq!(sub.entitlement, 2);
+        assert!(verify_thelgamal::<G1>(pp, pk, 4, &sub));
+        let sum = |sub: &ElGamalSubmission<G1>| {
+            let sum = sub.slots.iter().copied().reduce(thelgamal::add::<G1>);
+            thelgamal::decrypt::<G1>(pp, sk, sum.unwrap(), 8).unwrap()
+        };
+        assert_eq!(sum(&sub), F::from(2u64));
+
+        // Not taking part, without revealing the entitlement
+        let absent = submit_thelgamal::<G1>(pp, pk, 4, tag, 3, &[false; MAX_ENTITLEMENT]).unwrap();
+        assert!(verify_thelgamal::<G1>(pp, pk, 4, &absent));
+        assert_eq!(sum(&absent), F::from(0u64));
+
+        // Bound to the period, the tag and the entitlement
+        assert!(!verify_thelgamal::<G1>(pp, pk, 5, &sub));
+        let mut other = sub.clone();
+        other.period = 5;
+        assert!(!verify_thelgamal::<G1>(pp, pk, 5, &other));
+        let mut other = sub.clone();
+        other.tag = [8u8; TAG_BYTELEN];
+        assert!(!verify_thelgamal::<G1>(pp, pk, 4, &other));
+        let mut other = sub.clone();
+        other.entitlement = 1;
+        assert!(!verify_thelgamal::<G1>(pp, pk, 4, &other));
+
+        // A slot swapped for an encryption of 2
+        let mut other = sub.clone();
+        other.slots[1] = thelgamal::encrypt::<G1>(pp, pk, F::from(2u64));
+        assert!(!verify_thelgamal::<G1>(pp, pk, 4, &other));
+
+        // Valid bits adding up to neither 0 nor the entitlement
+        let mut other = sub.clone();
+        let r = F::from(5u64);
+        other.slots[3] = thelgamal::encrypt_with_randomness::<G1>(pp, pk, F::from(0u64), r);
+        other.bit_proofs[3] = prove_bit::<G1, G1>(
+            &label(ELGAMAL_LABEL, 4, &tag, 2),
+            &[thelgamal::bit_instance(pp, pk, &other.slots[3])],
+            &[],
+            false,
+            &[r],
+        );
+        assert!(!verify_thelgamal::<G1>(pp, pk, 4, &other));
+
+        assert_eq!(
+            submit_thelgamal::<G1>(pp, pk, 4, tag, 2, &[bits.as_slice(), &[false]].concat())
+                .unwrap_err(),
+            SubmissionError::SlotCount(MAX_ENTITLEMENT + 1)
+        );
+        assert_eq!(
+            submit_thelgamal::<G1>(pp, pk, 4, tag, 0, &[false; MAX_ENTITLEMENT]).unwrap_err(),
+            SubmissionError::EntitlementOutOfRange(0)
+        );
+        assert_eq!(
+            submit_thelgamal::<G1>(pp, pk, 4, tag, 3, &bits).unwrap_err(),
+            SubmissionError::InvalidSum(2)
+        );
+    }
+
+    #[test]
+    fn test_thbgn_submission() {
+        let pp = thbgn::paramgen::<P>();
+        let (_, pk) = thbgn::keygen::<P>(pp);
+        let tag = [7u8; TAG_BYTELEN];
+        let mut bits = [false; MAX_ENTITLEMENT];
+        bits[2] = true;
+
+        let sub = submit_thbgn::<P>(pp, pk, 4, tag, 1, &bits).unwrap();
+        assert_eq!(sub.entitlement, 1);
+        assert!(verify_thbgn::<P>(pp, pk, 4, &sub));
+        assert!(!verify_thbgn::<P>(pp, pk, 3, &sub));
+
+        let mut other = sub.clone();
+        other.entitlement = 0;
+        assert!(!verify_thbgn::<P>(pp, pk, 4, &other));
+        let mut other = sub.clone();
+        other.entitlement = 2;
+        assert!(!verify_thbgn::<P>(pp, pk, 4, &other));
+        let mut other = sub.clone();
+        other.slots.swap(0, 2);
+        assert!(!verify_thbgn::<P>(pp, pk, 4, &other));
+
+        // Fewer slots than MAX_ENTITLEMENT
+        assert_eq!(
+            submit_thbgn::<P>(pp, pk, 4, tag, 1, &bits[..2]).unwrap_err(),
+            SubmissionError::SlotCount(2)
+        );
+        let mut short = sub.clone();
+        short.slots.pop();
+        short.bit_proofs.pop();
+        assert!(!verify_thbgn::<P>(pp, pk, 4, &short));
+    }
+}
@@ -0,0 +1,154 @@
+//! Secret tag issuance: each recipient gets a secret from which their MAX_ENTITLEMENT tags for any
+//! period are derived with a PRF, and the issuer records each recipient's entitlement so that the
+//! auditor can check the entitlement a submission claims.
+use std::collections::BTreeMap;
+
+use openssl::hash::MessageDigest;
+use openssl::pkey::PKey;
+use openssl::sign::Signer;
+use rand::Rng;
+use rand::thread_rng;
+
+use crate::auditor::TagRegistry;
+use crate::{MAX_ENTITLEMENT, MIN_ENTITLEMENT, TAG_BYTELEN};
+
+pub const TAG_SECRET_BYTELEN: usize = 32;
+
+pub type TagSecret = [u8; TAG_SECRET_BYTELEN];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TagError {
+    /// The entitlement is not in [MIN_ENTITLEMENT, MAX_ENTITLEMENT]
+    EntitlementOutOfRange(usize),
+    /// The recipient is already registered
+    AlreadyRegistered(usize),
+    /// The tag is already registered
+    DuplicateTag([u8; TAG_BYTELEN]),
+}
+
+impl std::fmt::Display for TagError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TagError::EntitlementOutOfRange(entitlement) => write!(
+                f,
+                "entitlement {} not in [{}, {}]",
+                entitlement, MIN_ENTITLEMENT, MAX_ENTITLEMENT
+            ),
+            TagError::AlreadyRegistered(recipient) => {
+                write!(f, "recipient {} already registered", recipient)
+            }
+            TagError::DuplicateTag(tag) => write!(f, "tag {:02x?} already registered", tag),
+        }
+    }
+}
+
+impl std::error::Error for TagError {}
+
+/// Tag k of `period`: HMAC-SHA256(secret, period || k) truncated to TAG_BYTELEN bytes
+pub fn derive_tag(secret: &TagSecret, period: u16, k: usize) -> [u8; TAG_BYTELEN] {
+    let key = PKey::hmac(secret).unwrap();
+    let mut signer = Signer::new(MessageDigest::sha256(), &key).unwrap();
+    let mut input = period.to_be_bytes().to_vec();
+    input.extend_from_slice(&(k as u64).to_be_bytes());
+    let mac = signer.sign_oneshot_to_vec(&input).unwrap();
+    mac[..TAG_BYTELEN].try_into().unwrap()
+}
+
+/// The first `count` tags of `period`, one per slot of a submission
+pub fn derive_tags(secret: &TagSecret, period: u16, count: usize) -> Vec<[u8; TAG_BYTELEN]> {
+    (0..count).map(|k| derive_tag(secret, period, k)).collect()
+}
+
+/// Keeps the tag secret and entitlement of every registered recipient
+#[derive(Clone, Debug, Default)]
+pub struct TagIssuer {
+    recipients: BTreeMap<usize, (TagSecret, usize)>,
+}
+
+impl TagIssuer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register `recipient` with `entitlement`, and return the secret to hand them
+    pub fn register(
+        &mut self,
+        recipient: usize,
+        entitlement: usize,
+    ) -> Result<TagSecret, TagError> {
+        if !(MIN_ENTITLEMENT..=MAX_ENTITLEMENT).contains(&entitlement) {
+            return Err(TagError::EntitlementOutOfRange(entitlement));
+        }
+        if self.recipients.contains_key(&recipient) {
+            return Err(TagError::AlreadyRegistered(recipient));
+        }
+        let mut secret = [0u8; TAG_SECRET_BYTELEN];
+        thread_rng().fill(&mut secret);
+        self.recipients.insert(recipient, (secret, entitlement));
+        Ok(secret)
+    }
+
+    pub fn entitlement(&self, recipient: usize) -> Option<usize> {
+        self.recipients
+            .get(&recipient)
+            .map(|(_, entitlement)| *entitlement)
+    }
+
+    /// MAX_ENTITLEMENT tags of each registered recipient for `period`, whatever their entitlement,
+    /// for the auditor. Fails only if two derived tags collide.
+    pub fn registry(&self, period: u16) -> Result<TagRegistry, TagError> {
+        let mut registry = TagRegistry::new(period);
+        for (recipient, (secret, entitlement)) in self.recipients.iter() {
+            registry.register(
+                *recipient,
+                *entitlement,
+                derive_tags(secret, period, MAX_ENTITLEMENT),
+            )?;
+        }
+        Ok(registry)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_derive_tags() {
+        let secret = [1u8; TAG_SECRET_BYTELEN];
+        let tags = derive_tags(&secret, 3, MAX_ENTITLEMENT);
+        assert_eq!(tags, derive_tags(&secret, 3, MAX_ENTITLEMENT));
+        assert_eq!(tags[1], derive_tag(&secret, 3, 1));
+        assert_ne!(tags[0], tags[1]);
+        assert_ne!(tags[0], derive_tag(&secret, 4, 0));
+        assert_ne!(tags[0], derive_tag(&[2u8; TAG_SECRET_BYTELEN], 3, 0));
+    }
+
+    #[test]
+    fn test_issuer() {
+        let mut issuer = TagIssuer::new();
+        let secret = issuer.register(0, MIN_ENTITLEMENT).unwrap();
+        issuer.register(1, MAX_ENTITLEMENT).unwrap();
+        assert_eq!(
+            issuer.register(2, MAX_ENTITLEMENT + 1).unwrap_err(),
+            TagError::EntitlementOutOfRange(MAX_ENTITLEMENT + 1)
+        );
+        assert_eq!(
+            issuer.register(0, MIN_ENTITLEMENT).unwrap_err(),
+            TagError::AlreadyRegistered(0)
+        );
+        assert_eq!(issuer.entitlement(1), Some(MAX_ENTITLEMENT));
+        assert_eq!(issuer.entitlement(2), None);
+
+        let registry = issuer.registry(5).unwrap();
+        assert_eq!(registry.period(), 5);
+        // Every recipient gets MAX_ENTITLEMENT tags, so the number of slots hides the entitlement
+        assert_eq!(registry.len(), 2 * MAX_ENTITLEMENT);
+        assert_eq!(registry.entitlement(0), Some(MIN_ENTITLEMENT));
+        for tag in derive_tags(&secret, 5, MAX_ENTITLEMENT) {
+            assert_eq!(registry.owner(&tag), Some(0));
+        }
+        // Tags of another period are not valid
+        assert!(!registry.contains(&derive_tag(&secret, 6, 0)));
+    }
+}
@@ -0,0 +1,13 @@
+//! Fixtures shared by the tests of the protocol roles
+use tink_core::keyset;
+
+/// Fresh private encryption and signing keysets, as held by the helper or the auditor
+pub fn keys() -> (keyset::Handle, keyset::Handle) {
+    tink_hybrid::init();
+    tink_signature::init();
+    (
+        keyset::Handle::new(&tink_hybrid::ecies_hkdf_aes128_ctr_hmac_sha256_key_template())
+            .unwrap(),
+        keyset::Handle::new(&tink_signature::ecdsa_p256_key_template()).unwrap(),
+    )
+}
@@ -1,7 +1,9 @@
 use ark_ec::pairing;
 use ark_ec::pairing::*;
+use ark_ec::scalar_mul::fixed_base::FixedBase;
+use ark_ec::{ScalarMul, VariableBaseMSM};
 use ark_std::Zero;
-use ark_ff::Field;
+use ark_ff::{Field, PrimeField};
 use ark_std::UniformRand;
 use bincode::de;
 use rand::rngs::ThreadRng;
@@ -13,15 +15,28 @@ use secret_sharing_and_dkg::shamir_ss::*;
 use ark_std::cfg_into_iter;
 use ark_std::One;
 
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
+
+use crate::dlog::BabyStepTable;
+use crate::proofs::{
+    self, BitProof, DleqProof, ElGamalInstance, EqualityProof, Transcript, prove_bit, prove_dleq,
+    prove_equality, verify_dleq, verify_equality,
+};
+use crate::vss::{self, Commitments, ShareError};
+
 pub type PublicParameters<P: Pairing> = (P::G1, P::G2);
 pub type SecretKey<P: Pairing> = (P::ScalarField, P::ScalarField);
 pub type SecretKeyShare<P: Pairing> = (ShareId, P::ScalarField, P::ScalarField);
 
 pub type PublicKey<P: Pairing> = (P::G1, P::G2);
-#[derive(Clone, Copy)]
+/// Feldman commitments to the sharing polynomials of s1 (in G1) and s2 (in G2)
+pub type ShareCommitments<P: Pairing> = (Commitments<P::G1>, Commitments<P::G2>);
+/// Public key lifted to GT: (e(g1, g2), e(h1, g2), e(g1, h2))
+pub type PublicKeyT<P: Pairing> = (PairingOutput<P>, PairingOutput<P>, PairingOutput<P>);
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Ciphertext1<P: Pairing>(pub (P::G1, P::G1, P::G2, P::G2));
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct CiphertextT<P: Pairing>(
     pub (
         PairingOutput<P>,
@@ -42,6 +57,172 @@ pub type PartialDecryption<P: Pairing> = (
 pub type PartialDecryption2<P: Pairing> = (ShareId, PairingOutput<P>, PairingOutput<P>);
 pub type IntermediateDec<P> = (PairingOutput<P>, PairingOutput<P>);
 
+/// Verification key (share id, s1_i g1, s2_i g2) of a key share
+pub type VerificationKey<P: Pairing> = (ShareId, P::G1, P::G2);
+/// Proofs that a partial decryption used the s1 and s2 of a verification key
+pub type PartialDecryptionProof<P: Pairing> =
+    (DleqProof<P::ScalarField>, DleqProof<P::ScalarField>);
+pub type PartialDecryption2Proof<P: Pairing> = DleqProof<P::ScalarField>;
+/// Proof that the G1 and G2 halves of a ciphertext encrypt the same message
+pub type CiphertextProof<P: Pairing> = EqualityProof<P::ScalarField>;
+
+/// Shares of s1, s2 and s1 * s2, for single-round threshold decryption
+pub type SecretKeyShare1Round<P: Pairing> =
+    (ShareId, P::ScalarField, P::ScalarField, P::ScalarField);
+pub type PartialDecryption1Round<P: Pairing> = (ShareId, PairingOutput<P>);
+
+/// Fixed-base window tables for g1, h1, g2 and h2, for encrypting many messages under the same
+/// public key
+pub struct EncryptionTables<P: Pairing> {
+    window: usize,
+    g1: Vec<Vec<<P::G1 as ScalarMul>::MulBase>>,
+    h1: Vec<Vec<<P::G1 as ScalarMul>::MulBase>>,
+    g2: Vec<Vec<<P::G2 as ScalarMul>::MulBase>>,
+    h2: Vec<Vec<<P::G2 as ScalarMul>::MulBase>>,
+}
+
+/// Baby-step table for discrete logarithms in base e(g1, g2)
+pub type DlogTable<P> = BabyStepTable<PairingOutput<P>>;
+
+/// Encryptions of the bits of a message, least significant first, with proofs that each is a bit
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RangeProof<P: Pairing> {
+    pub bits: Vec<Ciphertext1<P>>,
+    pub proofs: Vec<BitProof<P::ScalarField>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ThbgnError {
+    /// The plaintext is not in (-bound, bound)
+    DlogOutOfBound(u64),
+    /// Invalid share ids or threshold
+    Share(ShareError),
+    /// A partial decryption carries a different public component than the others
+    InconsistentC4(ShareId),
+    /// The message is not in [0, 2^num_bits), or num_bits is not in [1, 63]
+    OutOfRange(u64, usize),
+    /// Undecodable bytes
+    MalformedEncoding,
+    /// Two ciphertext lists that should pair up have different lengths
+    LengthMismatch(usize, usize),
+}
+
+impl std::fmt::Display for ThbgnError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ThbgnError::DlogOutOfBound(bound) => {
+                write!(f, "discrete log not found in (-{}, {})", bound, bound)
+            }
+            ThbgnError::Share(err) => err.fmt(f),
+            ThbgnError::InconsistentC4(id) => {
+                write!(f, "inconsistent c4 component in share {}", id)
+            }
+            ThbgnError::OutOfRange(msg, num_bits) => {
+                write!(f, "{} is not a {}-bit message", msg, num_bits)
+            }
+            ThbgnError::MalformedEncoding => write!(f, "malformed encoding"),
+            ThbgnError::LengthMismatch(len_a, len_b) => {
+                write!(f, "lists of {} and {} ciphertexts", len_a, len_b)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ThbgnError {}
+
+impl From<ShareError> for ThbgnError {
+    fn from(err: ShareError) -> Self {
+        ThbgnError::Share(err)
+    }
+}
+
+impl From<SerializationError> for ThbgnError {
+    fn from(_: SerializationError) -> Self {
+        ThbgnError::MalformedEncoding
+    }
+}
+
+impl<P: Pairing> Ciphertext1<P> {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.0.serialize_compressed(&mut bytes).unwrap();
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ThbgnError> {
+        let mut reader = bytes;
+        let ct = <(P::G1, P::G1, P::G2, P::G2)>::deserialize_compressed(&mut reader)?;
+        if !reader.is_empty() {
+            return Err(ThbgnError::MalformedEncoding);
+        }
+        Ok(Ciphertext1(ct))
+    }
+}
+
+impl<P: Pairing> CanonicalSerialize for RangeProof<P> {
+    fn serialize_with_mode<W: ark_serialize::Write>(
+        &self,
+        mut writer: W,
+        compress: ark_serialize::Compress,
+    ) -> Result<(), SerializationError> {
+        let bits = self.bits.iter().map(|ct| ct.0).collect::<Vec<_>>();
+        bits.serialize_with_mode(&mut writer, compress)?;
+        self.proofs.serialize_with_mode(&mut writer, compress)
+    }
+
+    fn serialized_size(&self, compress: ark_serialize::Compress) -> usize {
+        let bits = self.bits.iter().map(|ct| ct.0).collect::<Vec<_>>();
+        bits.serialized_size(compress) + self.proofs.serialized_size(compress)
+    }
+}
+
+impl<P: Pairing> ark_serialize::Valid for RangeProof<P> {
+    fn check(&self) -> Result<(), SerializationError> {
+        Ok(())
+    }
+}
+
+impl<P: Pairing> CanonicalDeserialize for RangeProof<P> {
+    fn deserialize_with_mode<R: ark_serialize::Read>(
+        mut reader: R,
+        compress: ark_serialize::Compress,
+        validate: ark_serialize::Validate,
+    ) -> Result<Self, SerializationError> {
+        let bits = Vec::<(P::G1, P::G1, P::G2, P::G2)>::deserialize_with_mode(
+            &mut reader,
+            compress,
+            validate,
+        )?;
+        let proofs = Vec::deserialize_with_mode(&mut reader, compress, validate)?;
+        Ok(RangeProof {
+            bits: bits.into_iter().map(Ciphertext1).collect(),
+            proofs,
+        })
+    }
+}
+
+impl<P: Pairing> CiphertextT<P> {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.0.serialize_compressed(&mut bytes).unwrap();
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ThbgnError> {
+        let mut reader = bytes;
+        let ct = <(
+            PairingOutput<P>,
+            PairingOutput<P>,
+            PairingOutput<P>,
+            PairingOutput<P>,
+        )>::deserialize_compressed(&mut reader)?;
+        if !reader.is_empty() {
+            return Err(ThbgnError::MalformedEncoding);
+        }
+        Ok(CiphertextT(ct))
+    }
+}
+
 pub fn rand_invertible<F: Field>() -> F {
     let mut rng = thread_rng();
     loop {
@@ -74,6 +255,16 @@ pub fn encrypt<P: Pairing>(
     let mut rng = thread_rng();
     let rho = P::ScalarField::rand(&mut rng);
     let sigma = P::ScalarField::rand(&mut rng);
+    encrypt_with_randomness(pp, pk, msg, rho, sigma)
+}
+
+pub(crate) fn encrypt_with_randomness<P: Pairing>(
+    pp: PublicParameters<P>,
+    pk: PublicKey<P>,
+    msg: P::ScalarField,
+    rho: P::ScalarField,
+    sigma: P::ScalarField,
+) -> Ciphertext1<P> {
     let (g1, g2) = pp;
     let (h1, h2) = pk;
     Ciphertext1((
@@ -84,6 +275,190 @@ pub fn encrypt<P: Pairing>(
     ))
 }
 
+const CTXT_LABEL: &[u8] = b"thbgn ciphertext";
+
+/// Same as `encrypt`, with a proof that both halves of the ciphertext encrypt `msg`
+pub fn encrypt_with_proof<P: Pairing>(
+    pp: PublicParameters<P>,
+    pk: PublicKey<P>,
+    msg: P::ScalarField,
+) -> (Ciphertext1<P>, CiphertextProof<P>) {
+    let mut rng = thread_rng();
+    let rho = P::ScalarField::rand(&mut rng);
+    let sigma = P::ScalarField::rand(&mut rng);
+    let ct = encrypt_with_randomness(pp, pk, msg, rho, sigma);
+    let (a, b) = elgamal_instances(pp, pk, &ct);
+    let proof = prove_equality(CTXT_LABEL, &a[0], &b[0], msg, rho, sigma);
+    (ct, proof)
+}
+
+/// Check that the G1 and G2 halves of `ct` encrypt the same message, so that `mul` is meaningful
+pub fn verify_ciphertext<P: Pairing>(
+    pp: PublicParameters<P>,
+    pk: PublicKey<P>,
+    ct: &Ciphertext1<P>,
+    proof: &CiphertextProof<P>,
+) -> bool {
+    let (a, b) = elgamal_instances(pp, pk, ct);
+    verify_equality(CTXT_LABEL, &a[0], &b[0], proof)
+}
+
+const BIT_LABEL: &[u8] = b"thbgn bit";
+
+/// Label of a bit proof made in `context`
+fn bit_label(context: &[u8]) -> Vec<u8> {
+    [BIT_LABEL, context].concat()
+}
+
+pub(crate) fn elgamal_instances<P: Pairing>(
+    pp: PublicParameters<P>,
+    pk: PublicKey<P>,
+    ct: &Ciphertext1<P>,
+) -> ([ElGamalInstance<P::G1>; 1], [ElGamalInstance<P::G2>; 1]) {
+    let (g1, g2) = pp;
+    let (h1, h2) = pk;
+    let (c1, c2, c3, c4) = ct.0;
+    ([(g1, h1, c1, c2)], [(g2, h2, c3, c4)])
+}
+
+/// Encrypt a bit together with a proof that both halves of the ciphertext encrypt the same bit.
+/// The proof is bound to `context`, e.g. the period and tag of the submission it belongs to, and
+/// only verifies in the same context.
+pub fn encrypt_bit_with_proof<P: Pairing>(
+    pp: PublicParameters<P>,
+    pk: PublicKey<P>,
+    bit: bool,
+    context: &[u8],
+) -> (Ciphertext1<P>, BitProof<P::ScalarField>) {
+    let mut rng = thread_rng();
+    let rho = P::ScalarField::rand(&mut rng);
+    let sigma = P::ScalarField::rand(&mut rng);
+    let ct = encrypt_with_randomness(pp, pk, P::ScalarField::from(bit as u64), rho, sigma);
+    let (a, b) = elgamal_instances(pp, pk, &ct);
+    let proof = prove_bit(&bit_label(context), &a, &b, bit, &[rho, sigma]);
+    (ct, proof)
+}
+
+/// Check a proof made in `context` that the G1 and G2 halves of `ct` encrypt the same bit
+pub fn verify_bit<P: Pairing>(
+    pp: PublicParameters<P>,
+    pk: PublicKey<P>,
+    ct: &Ciphertext1<P>,
+    proof: &BitProof<P::ScalarField>,
+    context: &[u8],
+) -> bool {
+    let (a, b) = elgamal_instances(pp, pk, ct);
+    proofs::verify_bit(&bit_label(context), &a, &b, proof)
+}
+
+/// Encrypt `msg` together with a proof that it is in [0, 2^num_bits), so that products of such
+/// ciphertexts stay within a known discrete-log bound. The proof is bound to `context`, see
+/// `encrypt_bit_with_proof`.
+pub fn encrypt_with_range_proof<P: Pairing>(
+    pp: PublicParameters<P>,
+    pk: PublicKey<P>,
+    msg: u64,
+    num_bits: usize,
+    context: &[u8],
+) -> Result<(Ciphertext1<P>, RangeProof<P>), ThbgnError> {
+    if num_bits == 0 || num_bits > 63 || msg >> num_bits != 0 {
+        return Err(ThbgnError::OutOfRange(msg, num_bits));
+    }
+    let (bits, proofs) = (0..num_bits)
+        .map(|i| encrypt_bit_with_proof::<P>(pp, pk, (msg >> i) & 1 == 1, context))
+        .unzip::<_, _, Vec<_>, Vec<_>>();
+    // The randomness of the bit encryptions adds up to uniform randomness for msg
+    let ct = recompose::<P>(&bits);
+    Ok((ct, RangeProof { bits, proofs }))
+}
+
+/// Check that `ct` encrypts a message in [0, 2^num_bits), with a proof made in `context`
+pub fn verify_range<P: Pairing>(
+    pp: PublicParameters<P>,
+    pk: PublicKey<P>,
+    ct: &Ciphertext1<P>,
+    num_bits: usize,
+    proof: &RangeProof<P>,
+    context: &[u8],
+) -> bool {
+    proof.bits.len() == num_bits
+        && proof.proofs.len() == num_bits
+        && proof
+            .bits
+            .iter()
+            .zip(proof.proofs.iter())
+            .all(|(bit, bit_proof)| verify_bit::<P>(pp, pk, bit, bit_proof, context))
+        && recompose::<P>(&proof.bits) == *ct
+}
+
+/// sum_i 2^i bits[i]
+fn recompose<P: Pairing>(bits: &[Ciphertext1<P>]) -> Ciphertext1<P> {
+    let two = P::ScalarField::from(2u64);
+    let zero = Ciphertext1((
+        P::G1::zero(),
+        P::G1::zero(),
+        P::G2::zero(),
+        P::G2::zero(),
+    ));
+    bits.iter().rev().fold(zero, |acc, bit| {
+        let (a1, a2, a3, a4) = acc.0;
+        let (b1, b2, b3, b4) = bit.0;
+        Ciphertext1((a1 * two + b1, a2 * two + b2, a3 * two + b3, a4 * two + b4))
+    })
+}
+
+/// Precompute tables for `encrypt_batch`, sized for about `num_scalars` scalar multiplications
+/// per base.
+pub fn encryption_tables<P: Pairing>(
+    pp: PublicParameters<P>,
+    pk: PublicKey<P>,
+    num_scalars: usize,
+) -> EncryptionTables<P> {
+    let window = FixedBase::get_mul_window_size(num_scalars);
+    let scalar_size = P::ScalarField::MODULUS_BIT_SIZE as usize;
+    let (g1, g2) = pp;
+    let (h1, h2) = pk;
+    EncryptionTables {
+        window,
+        g1: FixedBase::get_window_table(scalar_size, window, g1),
+        h1: FixedBase::get_window_table(scalar_size, window, h1),
+        g2: FixedBase::get_window_table(scalar_size, window, g2),
+        h2: FixedBase::get_window_table(scalar_size, window, h2),
+    }
+}
+
+/// Same as calling `encrypt` on each message, using fixed-base tables from `encryption_tables`.
+pub fn encrypt_batch<P: Pairing>(
+    tables: &EncryptionTables<P>,
+    msgs: &[P::ScalarField],
+) -> Vec<Ciphertext1<P>> {
+    let mut rng = thread_rng();
+    let rhos = (0..msgs.len())
+        .map(|_| P::ScalarField::rand(&mut rng))
+        .collect::<Vec<_>>();
+    let sigmas = (0..msgs.len())
+        .map(|_| P::ScalarField::rand(&mut rng))
+        .collect::<Vec<_>>();
+    let scalar_size = P::ScalarField::MODULUS_BIT_SIZE as usize;
+    let w = tables.window;
+    let g1_rho = FixedBase::msm::<P::G1>(scalar_size, w, &tables.g1, &rhos);
+    let g1_msg = FixedBase::msm::<P::G1>(scalar_size, w, &tables.g1, msgs);
+    let h1_rho = FixedBase::msm::<P::G1>(scalar_size, w, &tables.h1, &rhos);
+    let g2_sigma = FixedBase::msm::<P::G2>(scalar_size, w, &tables.g2, &sigmas);
+    let g2_msg = FixedBase::msm::<P::G2>(scalar_size, w, &tables.g2, msgs);
+    let h2_sigma = FixedBase::msm::<P::G2>(scalar_size, w, &tables.h2, &sigmas);
+    (0..msgs.len())
+        .map(|i| {
+            Ciphertext1((
+                g1_rho[i],
+                g1_msg[i] + h1_rho[i],
+                g2_sigma[i],
+                g2_msg[i] + h2_sigma[i],
+            ))
+        })
+        .collect()
+}
+
 pub fn add<P: Pairing>(
     pp: PublicParameters<P>,
     ct1: Ciphertext1<P>,
@@ -92,7 +467,16 @@ pub fn add<P: Pairing>(
     let (c1_1, c1_2, c1_3, c1_4) = ct1.0;
     let (c2_1, c2_2, c2_3, c2_4) = ct2.0;
     Ciphertext1((c1_1 + c2_1, c1_2 + c2_2, c1_3 + c2_3, c1_4 + c2_4))
-    // TODO: rerandomize
+}
+
+/// Same as `add`, with an output that is unlinkable to the inputs.
+pub fn add_rerandomized<P: Pairing>(
+    pp: PublicParameters<P>,
+    pk: PublicKey<P>,
+    ct1: Ciphertext1<P>,
+    ct2: Ciphertext1<P>,
+) -> Ciphertext1<P> {
+    rerandomize(pp, pk, add(pp, ct1, ct2))
 }
 
 pub fn mul<P: Pairing>(
@@ -108,31 +492,139 @@ pub fn mul<P: Pairing>(
         P::pairing(c1_2, c2_3),
         P::pairing(c1_2, c2_4),
     ))
-    // TODO: rerandomize?
+}
+
+/// Sum of the products `cts_a[i] * cts_b[i]`, with one multi-pairing (a single final
+/// exponentiation) per component instead of four pairings per product.
+pub fn inner_product<P: Pairing>(
+    pp: PublicParameters<P>,
+    cts_a: &[Ciphertext1<P>],
+    cts_b: &[Ciphertext1<P>],
+) -> Result<CiphertextT<P>, ThbgnError> {
+    if cts_a.len() != cts_b.len() {
+        return Err(ThbgnError::LengthMismatch(cts_a.len(), cts_b.len()));
+    }
+    let a1 = cts_a.iter().map(|ct| ct.0.0).collect::<Vec<_>>();
+    let a2 = cts_a.iter().map(|ct| ct.0.1).collect::<Vec<_>>();
+    let b3 = cts_b.iter().map(|ct| ct.0.2).collect::<Vec<_>>();
+    let b4 = cts_b.iter().map(|ct| ct.0.3).collect::<Vec<_>>();
+    Ok(CiphertextT((
+        P::multi_pairing(a1.iter().copied(), b3.iter().copied()),
+        P::multi_pairing(a1, b4.iter().copied()),
+        P::multi_pairing(a2.iter().copied(), b3),
+        P::multi_pairing(a2, b4),
+    )))
+}
+
+/// Same as `mul`, with an output that is unlinkable to the inputs.
+pub fn mul_rerandomized<P: Pairing>(
+    pp: PublicParameters<P>,
+    pk_t: PublicKeyT<P>,
+    ct1: Ciphertext1<P>,
+    ct2: Ciphertext1<P>,
+) -> CiphertextT<P> {
+    rerandomize_t(pk_t, mul(pp, ct1, ct2))
+}
+
+pub fn pk_t<P: Pairing>(pp: PublicParameters<P>, pk: PublicKey<P>) -> PublicKeyT<P> {
+    let (g1, g2) = pp;
+    let (h1, h2) = pk;
+    (P::pairing(g1, g2), P::pairing(h1, g2), P::pairing(g1, h2))
+}
+
+/// Add a fresh encryption of 0 to both the G1 and the G2 halves of `ct`.
+pub fn rerandomize<P: Pairing>(
+    pp: PublicParameters<P>,
+    pk: PublicKey<P>,
+    ct: Ciphertext1<P>,
+) -> Ciphertext1<P> {
+    let mut rng = thread_rng();
+    let rho = P::ScalarField::rand(&mut rng);
+    let sigma = P::ScalarField::rand(&mut rng);
+    let (g1, g2) = pp;
+    let (h1, h2) = pk;
+    let (c1, c2, c3, c4) = ct.0;
+    Ciphertext1((c1 + g1 * rho, c2 + h1 * rho, c3 + g2 * sigma, c4 + h2 * sigma))
+}
+
+/// Fresh level-2 encryption of `msg`, uniform among all level-2 encryptions of `msg`.
+pub fn encrypt_t<P: Pairing>(pk_t: PublicKeyT<P>, msg: P::ScalarField) -> CiphertextT<P> {
+    // Random combination of the public tensors (g1, h1) x gT, (g1, h1) x e(., h2),
+    // gT x (g2, h2) and e(h1, .) x (g2, h2), which span the decryption kernel
+    let mut rng = thread_rng();
+    let (r1, r2, r3, r4) = (
+        P::ScalarField::rand(&mut rng),
+        P::ScalarField::rand(&mut rng),
+        P::ScalarField::rand(&mut rng),
+        P::ScalarField::rand(&mut rng),
+    );
+    let (gT, hT1, hT2) = pk_t;
+    CiphertextT((
+        gT * (r1 + r3),
+        gT * r2 + hT2 * r3,
+        hT1 * r1 + gT * r4,
+        gT * msg + hT1 * r2 + hT2 * r4,
+    ))
+}
+
+/// Add a fresh encryption of 0 in GT to `ct`.
+pub fn rerandomize_t<P: Pairing>(pk_t: PublicKeyT<P>, ct: CiphertextT<P>) -> CiphertextT<P> {
+    add_t(ct, encrypt_t(pk_t, P::ScalarField::zero()))
+}
+
+pub fn add_t<P: Pairing>(ct1: CiphertextT<P>, ct2: CiphertextT<P>) -> CiphertextT<P> {
+    let (c1_1, c1_2, c1_3, c1_4) = ct1.0;
+    let (c2_1, c2_2, c2_3, c2_4) = ct2.0;
+    CiphertextT((c1_1 + c2_1, c1_2 + c2_2, c1_3 + c2_3, c1_4 + c2_4))
+}
+
+pub fn sub_t<P: Pairing>(ct1: CiphertextT<P>, ct2: CiphertextT<P>) -> CiphertextT<P> {
+    let (c1_1, c1_2, c1_3, c1_4) = ct1.0;
+    let (c2_1, c2_2, c2_3, c2_4) = ct2.0;
+    CiphertextT((c1_1 - c2_1, c1_2 - c2_2, c1_3 - c2_3, c1_4 - c2_4))
+}
+
+pub fn scalar_mul_t<P: Pairing>(ct: CiphertextT<P>, k: P::ScalarField) -> CiphertextT<P> {
+    let (c1, c2, c3, c4) = ct.0;
+    CiphertextT((c1 * k, c2 * k, c3 * k, c4 * k))
+}
+
+/// Turn a level-1 ciphertext into a level-2 ciphertext of the same message, by multiplying
+/// its G1 half with the trivial G2 encryption (0, g2) of 1. The output is linkable to `ct`
+/// unless rerandomized with `rerandomize_t`.
+pub fn lift<P: Pairing>(pp: PublicParameters<P>, ct: Ciphertext1<P>) -> CiphertextT<P> {
+    let (c1, c2, _, _) = ct.0;
+    let (_, g2) = pp;
+    let zero = PairingOutput::<P>::zero();
+    CiphertextT((zero, P::pairing(c1, g2), zero, P::pairing(c2, g2)))
+}
+
+pub fn dlog_table<P: Pairing>(pp: PublicParameters<P>, bound: u64) -> DlogTable<P> {
+    BabyStepTable::new(P::pairing(pp.0, pp.1), bound)
 }
 
 pub fn find_dlog<P: Pairing>(
     base: PairingOutput<P>,
     p: PairingOutput<P>,
-    bound: P::ScalarField,
-) -> P::ScalarField
-where
-    P::ScalarField: ark_ff::PrimeField,
-{
-    // Find discrete log of p in base
-    // Enumerate from -Bound to Bound in F, test if base^i = p
+    bound: u64,
+) -> Result<P::ScalarField, ThbgnError> {
+    // Find discrete log of p in base in (-bound, bound) with baby-step giant-step
+    crate::dlog::find_dlog(base, p, bound).ok_or(ThbgnError::DlogOutOfBound(bound))
+}
 
-    let mut i = P::ScalarField::zero();
-    while i < bound {
-        if base * i == p {
-            return i;
-        }
-        if base * i == -p {
-            return -i;
-        }
-        i += P::ScalarField::ONE;
-    }
-    panic!("Discrete log not found");
+pub fn find_dlog_with_table<P: Pairing>(
+    table: &DlogTable<P>,
+    p: PairingOutput<P>,
+) -> Result<P::ScalarField, ThbgnError> {
+    table
+        .solve(p)
+        .ok_or(ThbgnError::DlogOutOfBound(table.bound()))
+}
+
+fn decrypt_in_exp<P: Pairing>(sk: SecretKey<P>, ct: CiphertextT<P>) -> PairingOutput<P> {
+    let (s1, s2) = sk;
+    let (c1, c2, c3, c4) = ct.0;
+    c1 * (s1 * s2) - c2 * s1 - c3 * s2 + c4
 }
 
 pub fn decrypt<P: Pairing>(
@@ -140,43 +632,164 @@ pub fn decrypt<P: Pairing>(
     sk: SecretKey<P>,
     ct: CiphertextT<P>,
     bound: u64,
-) -> P::ScalarField {
-    let (s1, s2) = sk;
-    let (c1, c2, c3, c4) = ct.0;
+) -> Result<P::ScalarField, ThbgnError> {
     let (g1, g2) = pp;
     let gT = P::pairing(g1, g2);
-    find_dlog(
-        gT,
-        c1 * (s1 * s2) - c2 * s1 - c3 * s2 + c4,
-        P::ScalarField::from(bound),
-    )
+    find_dlog(gT, decrypt_in_exp(sk, ct), bound)
+}
+
+/// Same as `decrypt`, with a table precomputed by `dlog_table` for the same public parameters.
+pub fn decrypt_with_table<P: Pairing>(
+    table: &DlogTable<P>,
+    sk: SecretKey<P>,
+    ct: CiphertextT<P>,
+) -> Result<P::ScalarField, ThbgnError> {
+    find_dlog_with_table(table, decrypt_in_exp(sk, ct))
 }
 
 pub fn share_sk<P: Pairing>(
     sk: SecretKey<P>,
     threshold: usize,
     total: usize,
-) -> Vec<SecretKeyShare<P>>
+) -> Result<Vec<SecretKeyShare<P>>, ThbgnError>
 where
     <P as ark_ec::pairing::Pairing>::ScalarField: ark_ff::PrimeField,
 {
+    if threshold == 0 || threshold > total || total > ShareId::MAX as usize {
+        return Err(ShareError::InvalidThreshold(threshold, total).into());
+    }
     let mut rng = thread_rng();
 
-    let (shares1, poly1) =
-        deal_secret(&mut rng, sk.0, threshold as ShareId, total as ShareId).unwrap();
-    let (shares2, poly2) =
-        deal_secret(&mut rng, sk.1, threshold as ShareId, total as ShareId).unwrap();
+    let (shares1, poly1) = deal_secret(&mut rng, sk.0, threshold as ShareId, total as ShareId)
+        .map_err(|_| ShareError::InvalidThreshold(threshold, total))?;
+    let (shares2, poly2) = deal_secret(&mut rng, sk.1, threshold as ShareId, total as ShareId)
+        .map_err(|_| ShareError::InvalidThreshold(threshold, total))?;
     debug_assert!(shares1
         .0
         .iter()
         .zip(shares2.0.iter())
         .all(|(s1, s2)| s1.id == s2.id));
-    shares1
+    Ok(shares1
         .0
         .into_iter()
         .zip(shares2.0.into_iter())
         .map(|(s1, s2)| (s1.id, s1.share, s2.share))
-        .collect()
+        .collect())
+}
+
+/// Same as `share_sk`, additionally committing to the sharing polynomials of s1 in base g1 and
+/// of s2 in base g2. The commitments are published with the public key, so that every
+/// recipient can check its share with `verify_share`.
+pub fn share_sk_verifiable<P: Pairing>(
+    pp: PublicParameters<P>,
+    sk: SecretKey<P>,
+    threshold: usize,
+    total: usize,
+) -> Result<(Vec<SecretKeyShare<P>>, ShareCommitments<P>), ThbgnError> {
+    if threshold == 0 || threshold > total || total > ShareId::MAX as usize {
+        return Err(ShareError::InvalidThreshold(threshold, total).into());
+    }
+    let (g1, g2) = pp;
+    let (shares1, commitments1) = vss::deal(g1, sk.0, threshold as ShareId, total as ShareId)
+        .map_err(|_| ShareError::InvalidThreshold(threshold, total))?;
+    let (shares2, commitments2) = vss::deal(g2, sk.1, threshold as ShareId, total as ShareId)
+        .map_err(|_| ShareError::InvalidThreshold(threshold, total))?;
+    let shares = shares1
+        .into_iter()
+        .zip(shares2)
+        .map(|((id, s1), (_, s2))| (id, s1, s2))
+        .collect();
+    Ok((shares, (commitments1, commitments2)))
+}
+
+/// Check that `share` is consistent with `commitments`, and that `commitments` are for the
+/// secret key of `pk`.
+pub fn verify_share<P: Pairing>(
+    pp: PublicParameters<P>,
+    pk: PublicKey<P>,
+    commitments: &ShareCommitments<P>,
+    share: SecretKeyShare<P>,
+) -> bool {
+    let (g1, g2) = pp;
+    let (h1, h2) = pk;
+    let (commitments1, commitments2) = commitments;
+    let (id, s1, s2) = share;
+    commitments1.public_value() == h1
+        && commitments2.public_value() == h2
+        && commitments1.threshold() == commitments2.threshold()
+        && commitments1.verify(g1, id, &s1)
+        && commitments2.verify(g2, id, &s2)
+}
+
+/// Verification key (s1_i g1, s2_i g2) of share i, computed from the share commitments only
+pub fn verification_key<P: Pairing>(
+    commitments: &ShareCommitments<P>,
+    id: ShareId,
+) -> VerificationKey<P> {
+    (id, commitments.0.eval(id), commitments.1.eval(id))
+}
+
+const PDEC_LABEL: &[u8] = b"thbgn partial_decrypt";
+const PDEC2_LABEL: &[u8] = b"thbgn partial_decrypt2";
+
+/// Same as `partial_decrypt`, with a proof that the same s1 and s2 as in the verification key
+/// were used
+pub fn partial_decrypt_with_proof<P: Pairing>(
+    pp: PublicParameters<P>,
+    sk: SecretKeyShare<P>,
+    ct: CiphertextT<P>,
+) -> (PartialDecryption<P>, PartialDecryptionProof<P>) {
+    let (g1, g2) = pp;
+    let (c1, c2, c3, _) = ct.0;
+    let (_, s1, s2) = sk;
+    let pdec = partial_decrypt::<P>(pp, sk, ct);
+    let (_, d1, d2, d3, _) = pdec;
+    let proof1 = prove_dleq(PDEC_LABEL, (g1, g1 * s1), &[(c1, d1), (c2, d2)], s1);
+    let proof2 = prove_dleq(PDEC_LABEL, (g2, g2 * s2), &[(c3, d3)], s2);
+    (pdec, (proof1, proof2))
+}
+
+pub fn verify_partial_decryption<P: Pairing>(
+    pp: PublicParameters<P>,
+    vk: VerificationKey<P>,
+    ct: CiphertextT<P>,
+    pdec: PartialDecryption<P>,
+    proof: &PartialDecryptionProof<P>,
+) -> bool {
+    let (g1, g2) = pp;
+    let (c1, c2, c3, c4) = ct.0;
+    let (id, vk1, vk2) = vk;
+    let (pdec_id, d1, d2, d3, pdec_c4) = pdec;
+    id == pdec_id
+        && c4 == pdec_c4
+        && verify_dleq(PDEC_LABEL, (g1, vk1), &[(c1, d1), (c2, d2)], &proof.0)
+        && verify_dleq(PDEC_LABEL, (g2, vk2), &[(c3, d3)], &proof.1)
+}
+
+/// Same as `partial_decrypt2`, with a proof that the same s2 as in the verification key was used
+pub fn partial_decrypt2_with_proof<P: Pairing>(
+    pp: PublicParameters<P>,
+    sk: SecretKeyShare<P>,
+    idec: IntermediateDec<P>,
+) -> (PartialDecryption2<P>, PartialDecryption2Proof<P>) {
+    let (_, g2) = pp;
+    let (_, _, s2) = sk;
+    let pdec = partial_decrypt2::<P>(pp, sk, idec);
+    let proof = prove_dleq(PDEC2_LABEL, (g2, g2 * s2), &[(idec.0, pdec.1)], s2);
+    (pdec, proof)
+}
+
+pub fn verify_partial_decryption2<P: Pairing>(
+    pp: PublicParameters<P>,
+    vk: VerificationKey<P>,
+    idec: IntermediateDec<P>,
+    pdec: PartialDecryption2<P>,
+    proof: &PartialDecryption2Proof<P>,
+) -> bool {
+    let (_, g2) = pp;
+    let (id, _, vk2) = vk;
+    let (pdec_id, d, c) = pdec;
+    id == pdec_id && c == idec.1 && verify_dleq(PDEC2_LABEL, (g2, vk2), &[(idec.0, d)], proof)
 }
 
 pub fn partial_decrypt<P: Pairing>(
@@ -192,34 +805,36 @@ pub fn partial_decrypt<P: Pairing>(
 pub fn intermediate_dec<P: Pairing>(
     pp: PublicParameters<P>,
     pdecs: &[PartialDecryption<P>],
-    bound: u64,
-) -> IntermediateDec<P> {
+    threshold: usize,
+) -> Result<IntermediateDec<P>, ThbgnError> {
+    vss::check_share_ids(pdecs.iter().map(|pdec| pdec.0), threshold)?;
     let c4 = pdecs[0].4;
-    debug_assert!(pdecs.into_iter().map(|pdec| pdec.4).all(|c| c == c4));
+    if let Some(pdec) = pdecs.iter().find(|pdec| pdec.4 != c4) {
+        return Err(ThbgnError::InconsistentC4(pdec.0));
+    }
+    // Any threshold many shares determine the result
+    let pdecs = &pdecs[..threshold];
     // Reconstruct s1*c1, c2*s1, c3*s2
     let s1c1 = reconstruct_secret_in_exp(
         &pdecs
             .iter()
             .map(|pdec| (pdec.0, pdec.1))
             .collect::<Vec<_>>(),
-    )
-    .unwrap();
+    )?;
     let c1s2 = reconstruct_secret_in_exp(
         &pdecs
             .iter()
             .map(|pdec| (pdec.0, pdec.2))
             .collect::<Vec<_>>(),
-    )
-    .unwrap();
+    )?;
     let c3s2 = reconstruct_secret_in_exp(
         &pdecs
             .iter()
             .map(|pdec| (pdec.0, pdec.3))
             .collect::<Vec<_>>(),
-    )
-    .unwrap();
+    )?;
 
-    (s1c1, -c1s2 - c3s2 + c4)
+    Ok((s1c1, -c1s2 - c3s2 + c4))
 }
 
 pub fn partial_decrypt2<P: Pairing>(
@@ -232,44 +847,297 @@ pub fn partial_decrypt2<P: Pairing>(
     (id, s1c1 * s2, c)
 }
 
-pub fn final_decrypt<P: Pairing>(
-    pp: PublicParameters<P>,
+fn final_decrypt_in_exp<P: Pairing>(
     pdecs: &[PartialDecryption2<P>],
-    bound: u64,
-) -> P::ScalarField {
+    threshold: usize,
+) -> Result<PairingOutput<P>, ThbgnError> {
+    vss::check_share_ids(pdecs.iter().map(|pdec| pdec.0), threshold)?;
     let c = pdecs[0].2;
-    debug_assert!(pdecs.into_iter().map(|pdec| pdec.2).all(|c_| c_ == c));
+    if let Some(pdec) = pdecs.iter().find(|pdec| pdec.2 != c) {
+        return Err(ThbgnError::InconsistentC4(pdec.0));
+    }
     let s1s2c1 = reconstruct_secret_in_exp(
-        &pdecs
+        &pdecs[..threshold]
             .iter()
             .map(|pdec| (pdec.0, pdec.1))
             .collect::<Vec<_>>(),
+    )?;
+    Ok(s1s2c1 + c)
+}
+
+pub fn final_decrypt<P: Pairing>(
+    pp: PublicParameters<P>,
+    pdecs: &[PartialDecryption2<P>],
+    threshold: usize,
+    bound: u64,
+) -> Result<P::ScalarField, ThbgnError> {
+    find_dlog(
+        P::pairing(pp.0, pp.1),
+        final_decrypt_in_exp(pdecs, threshold)?,
+        bound,
     )
-    .unwrap();
+}
+
+/// Same as `final_decrypt`, with a table precomputed by `dlog_table` for the same public parameters.
+pub fn final_decrypt_with_table<P: Pairing>(
+    table: &DlogTable<P>,
+    pdecs: &[PartialDecryption2<P>],
+    threshold: usize,
+) -> Result<P::ScalarField, ThbgnError> {
+    find_dlog_with_table(table, final_decrypt_in_exp(pdecs, threshold)?)
+}
+
+/// Keep the first partial decryption of each share id whose proof verifies against its
+/// verification key in `vks`. Returns the kept partial decryptions and the ids of the rejected
+/// ones; a share id without a verification key is rejected.
+fn filter_verified<P: Pairing, D: Copy, Pr>(
+    vks: &[VerificationKey<P>],
+    pdecs: &[(D, Pr)],
+    id: impl Fn(&D) -> ShareId,
+    mut verify: impl FnMut(&D, &Pr, usize) -> bool,
+) -> (Vec<D>, Vec<ShareId>) {
+    let mut valid = Vec::with_capacity(pdecs.len());
+    let mut invalid = Vec::new();
+    let mut seen = std::collections::HashSet::new();
+    let index = vks
+        .iter()
+        .enumerate()
+        .map(|(i, vk)| (vk.0, i))
+        .collect::<std::collections::HashMap<_, _>>();
+    for (pdec, proof) in pdecs {
+        let pdec_id = id(pdec);
+        if seen.contains(&pdec_id) {
+            continue;
+        }
+        match index.get(&pdec_id) {
+            Some(&i) if pdec_id != 0 && verify(pdec, proof, i) => {
+                seen.insert(pdec_id);
+                valid.push(*pdec);
+            }
+            _ => invalid.push(pdec_id),
+        }
+    }
+    // A share id may have been rejected before a valid copy of it was found
+    invalid.retain(|id| !seen.contains(id));
+    invalid.sort();
+    invalid.dedup();
+    (valid, invalid)
+}
+
+/// Split partial decryptions of `ct` into those with a valid proof and the ids of the others.
+pub fn check_partial_decryptions<P: Pairing>(
+    pp: PublicParameters<P>,
+    vks: &[VerificationKey<P>],
+    ct: CiphertextT<P>,
+    pdecs: &[(PartialDecryption<P>, PartialDecryptionProof<P>)],
+) -> (Vec<PartialDecryption<P>>, Vec<ShareId>) {
+    filter_verified::<P, _, _>(vks, pdecs, |pdec| pdec.0, |pdec, proof, i| {
+        verify_partial_decryption::<P>(pp, vks[i], ct, *pdec, proof)
+    })
+}
+
+/// Split second-round partial decryptions of `idec` into those with a valid proof and the ids of
+/// the others.
+pub fn check_partial_decryptions2<P: Pairing>(
+    pp: PublicParameters<P>,
+    vks: &[VerificationKey<P>],
+    idec: IntermediateDec<P>,
+    pdecs: &[(PartialDecryption2<P>, PartialDecryption2Proof<P>)],
+) -> (Vec<PartialDecryption2<P>>, Vec<ShareId>) {
+    filter_verified::<P, _, _>(vks, pdecs, |pdec| pdec.0, |pdec, proof, i| {
+        verify_partial_decryption2::<P>(pp, vks[i], idec, *pdec, proof)
+    })
+}
+
+/// Same as `intermediate_dec`, but tolerates misbehaving recipients: partial decryptions with an
+/// invalid proof are excluded, and their share ids are returned alongside the result. Fails only
+/// if fewer than `threshold` valid partial decryptions remain.
+pub fn robust_intermediate_dec<P: Pairing>(
+    pp: PublicParameters<P>,
+    vks: &[VerificationKey<P>],
+    ct: CiphertextT<P>,
+    pdecs: &[(PartialDecryption<P>, PartialDecryptionProof<P>)],
+    threshold: usize,
+) -> Result<(IntermediateDec<P>, Vec<ShareId>), ThbgnError> {
+    let (valid, invalid) = check_partial_decryptions::<P>(pp, vks, ct, pdecs);
+    Ok((intermediate_dec::<P>(pp, &valid, threshold)?, invalid))
+}
+
+/// Same as `final_decrypt`, excluding and reporting second-round partial decryptions with an
+/// invalid proof.
+pub fn robust_final_decrypt<P: Pairing>(
+    pp: PublicParameters<P>,
+    vks: &[VerificationKey<P>],
+    idec: IntermediateDec<P>,
+    pdecs: &[(PartialDecryption2<P>, PartialDecryption2Proof<P>)],
+    threshold: usize,
+    bound: u64,
+) -> Result<(P::ScalarField, Vec<ShareId>), ThbgnError> {
+    let (valid, invalid) = check_partial_decryptions2::<P>(pp, vks, idec, pdecs);
+    Ok((final_decrypt::<P>(pp, &valid, threshold, bound)?, invalid))
+}
 
+/// Same as `robust_final_decrypt`, with a table precomputed by `dlog_table`.
+pub fn robust_final_decrypt_with_table<P: Pairing>(
+    pp: PublicParameters<P>,
+    table: &DlogTable<P>,
+    vks: &[VerificationKey<P>],
+    idec: IntermediateDec<P>,
+    pdecs: &[(PartialDecryption2<P>, PartialDecryption2Proof<P>)],
+    threshold: usize,
+) -> Result<(P::ScalarField, Vec<ShareId>), ThbgnError> {
+    let (valid, invalid) = check_partial_decryptions2::<P>(pp, vks, idec, pdecs);
+    Ok((final_decrypt_with_table::<P>(table, &valid, threshold)?, invalid))
+}
+
+/// Same as `share_sk`, additionally sharing s1 * s2 so that partial decryptions of level-2
+/// ciphertexts can be combined without a second round.
+pub fn share_sk_1round<P: Pairing>(
+    sk: SecretKey<P>,
+    threshold: usize,
+    total: usize,
+) -> Result<Vec<SecretKeyShare1Round<P>>, ThbgnError>
+where
+    <P as ark_ec::pairing::Pairing>::ScalarField: ark_ff::PrimeField,
+{
+    let shares = share_sk::<P>(sk, threshold, total)?;
+    let mut rng = thread_rng();
+    let (shares12, poly12) =
+        deal_secret(&mut rng, sk.0 * sk.1, threshold as ShareId, total as ShareId)
+            .map_err(|_| ShareError::InvalidThreshold(threshold, total))?;
+    debug_assert!(shares
+        .iter()
+        .zip(shares12.0.iter())
+        .all(|(s, s12)| s.0 == s12.id));
+    Ok(shares
+        .into_iter()
+        .zip(shares12.0.into_iter())
+        .map(|((id, s1, s2), s12)| (id, s1, s2, s12.share))
+        .collect())
+}
+
+pub fn partial_decrypt_1round<P: Pairing>(
+    pp: PublicParameters<P>,
+    sk: SecretKeyShare1Round<P>,
+    ct: CiphertextT<P>,
+) -> PartialDecryption1Round<P> {
+    let (c1, c2, c3, _) = ct.0;
+    let (id, s1, s2, s12) = sk;
+    (id, c1 * s12 - c2 * s1 - c3 * s2)
+}
+
+fn final_decrypt_1round_in_exp<P: Pairing>(
+    ct: CiphertextT<P>,
+    pdecs: &[PartialDecryption1Round<P>],
+    threshold: usize,
+) -> Result<PairingOutput<P>, ThbgnError> {
+    vss::check_share_ids(pdecs.iter().map(|pdec| pdec.0), threshold)?;
+    let c = reconstruct_secret_in_exp(&pdecs[..threshold])?;
+    Ok(c + ct.0.3)
+}
+
+pub fn final_decrypt_1round<P: Pairing>(
+    pp: PublicParameters<P>,
+    ct: CiphertextT<P>,
+    pdecs: &[PartialDecryption1Round<P>],
+    threshold: usize,
+    bound: u64,
+) -> Result<P::ScalarField, ThbgnError> {
     find_dlog(
         P::pairing(pp.0, pp.1),
-        s1s2c1 + c,
-        P::ScalarField::from(bound),
+        final_decrypt_1round_in_exp(ct, pdecs, threshold)?,
+        bound,
     )
 }
 
-/// Given ((i_j, s_i_j G))_{j\in[t]}, output s G where s_i_j = f(i_j) and s = f(0)
-pub fn reconstruct_secret_in_exp<P: Pairing>(
-    shares: &[(ShareId, PairingOutput<P>)],
-) -> Result<PairingOutput<P>, SSError> {
-    // let threshold = self.threshold();
-    // let len = self.0.len() as ShareId;
-    // if threshold > len {
-    //     return Err(SSError::BelowThreshold(threshold, len));
-    // }
-    // let shares = &self.0[0..threshold as usize];
+/// Same as `final_decrypt_1round`, with a table precomputed by `dlog_table`.
+pub fn final_decrypt_1round_with_table<P: Pairing>(
+    table: &DlogTable<P>,
+    ct: CiphertextT<P>,
+    pdecs: &[PartialDecryption1Round<P>],
+    threshold: usize,
+) -> Result<P::ScalarField, ThbgnError> {
+    find_dlog_with_table(table, final_decrypt_1round_in_exp(ct, pdecs, threshold)?)
+}
+
+/// Given ((i_j, s_i_j G))_{j\in[t]}, output s G where s_i_j = f(i_j) and s = f(0), using a
+/// multi-scalar multiplication. Works in G1, G2 and GT.
+pub fn reconstruct_secret_in_exp<G: VariableBaseMSM>(
+    shares: &[(ShareId, G)],
+) -> Result<G, ThbgnError> {
+    vss::check_share_ids(shares.iter().map(|s| s.0), 1)?;
     let share_ids = shares.iter().map(|s| s.0).collect::<Vec<_>>();
-    let basis = lagrange_basis_at_0_for_all::<P::ScalarField>(share_ids)?;
-    Ok(cfg_into_iter!(basis)
-        .zip(cfg_into_iter!(shares))
-        .map(|(b, s)| s.1* b)
-        .sum::<PairingOutput<P>>())
+    let basis = lagrange_basis_at_0_for_all::<G::ScalarField>(share_ids)
+        .map_err(|_| ThbgnError::MalformedEncoding)?;
+    let bases = G::batch_convert_to_mul_base(&shares.iter().map(|s| s.1).collect::<Vec<_>>());
+    Ok(G::msm_unchecked(&bases, &basis))
+}
+
+/// Output of the helper for a period: for each input list [c_0, c_1, ...], the products
+/// [c_0 * c_1, ...], with a digest of the inputs so that anyone given them can recompute the
+/// products.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HelperOutput<P: Pairing> {
+    pub period: u16,
+    pub inputs_digest: [u8; 32],
+    pub output: Vec<Vec<CiphertextT<P>>>,
+}
+
+impl<P: Pairing> HelperOutput<P> {
+    /// Bytes signed by the helper. Every list is prefixed with its length, so that the signature
+    /// also fixes the shape of the output.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.period.to_be_bytes().to_vec();
+        bytes.extend_from_slice(&self.inputs_digest);
+        bytes.extend_from_slice(&(self.output.len() as u64).to_be_bytes());
+        for cts in self.output.iter() {
+            bytes.extend_from_slice(&(cts.len() as u64).to_be_bytes());
+            for ct in cts {
+                bytes.extend_from_slice(&ct.to_bytes());
+            }
+        }
+        bytes
+    }
+}
+
+pub fn inputs_digest<P: Pairing>(period: u16, inputs: &[Vec<Ciphertext1<P>>]) -> [u8; 32] {
+    let mut transcript = Transcript::new(b"thbgn helper inputs");
+    transcript.append_bytes(&period.to_be_bytes());
+    transcript.append_bytes(&(inputs.len() as u64).to_be_bytes());
+    for cts in inputs {
+        transcript.append(&cts.iter().map(|ct| ct.0).collect::<Vec<_>>());
+    }
+    transcript.digest()
+}
+
+/// f2: multiply the first ciphertext of each input list with each of the others
+pub fn evaluate_helper<P: Pairing>(
+    pp: PublicParameters<P>,
+    period: u16,
+    inputs: &[Vec<Ciphertext1<P>>],
+) -> HelperOutput<P> {
+    let output = inputs
+        .iter()
+        .map(|cs| match cs.split_first() {
+            Some((c0, rest)) => rest.iter().map(|c| mul::<P>(pp, *c0, *c)).collect(),
+            None => Vec::new(),
+        })
+        .collect();
+    HelperOutput {
+        period,
+        inputs_digest: inputs_digest(period, inputs),
+        output,
+    }
+}
+
+/// Check that `output` holds the products of `inputs`, and that `inputs` is the list the helper
+/// committed to
+pub fn verify_helper_output<P: Pairing>(
+    pp: PublicParameters<P>,
+    inputs: &[Vec<Ciphertext1<P>>],
+    output: &HelperOutput<P>,
+) -> bool {
+    evaluate_helper(pp, output.period, inputs) == *output
 }
 
 #[cfg(test)]
@@ -291,7 +1159,7 @@ mod test {
         let ct0 = encrypt::<P>(pp, pk, msg);
         let ct1 = encrypt::<P>(pp, pk, msg);
         let ct2 = mul::<P>(pp, ct0, ct1);
-        let pt = decrypt::<P>(pp, sk, ct2, bound);
+        let pt = decrypt::<P>(pp, sk, ct2, bound).unwrap();
         assert_eq!(msg * msg, pt);
     }
 
@@ -300,7 +1168,7 @@ mod test {
         let pp = paramgen::<P>();
 
         let (sk, pk) = keygen::<P>(pp);
-        let sks = share_sk::<P>(sk, 3, 5);
+        let sks = share_sk::<P>(sk, 3, 5).unwrap();
 
         let msg = F::from(ptxt);
         debug_assert!(ptxt * ptxt <= bound);
@@ -312,14 +1180,474 @@ mod test {
             .iter()
             .map(|sk| partial_decrypt::<P>(pp, *sk, ct2))
             .collect::<Vec<_>>();
-        let inter = intermediate_dec::<P>(pp, pdecs.as_slice(), bound);
+        let inter = intermediate_dec::<P>(pp, pdecs.as_slice(), 3).unwrap();
 
         let pdecs2 = sks
             .iter()
             .map(|sk| partial_decrypt2::<P>(pp, *sk, inter))
             .collect::<Vec<_>>();
 
-        let pt = final_decrypt::<P>(pp, &pdecs2, (1 << 4));
+        let pt = final_decrypt::<P>(pp, &pdecs2, 3, (1 << 4)).unwrap();
         assert_eq!(msg * msg, pt);
+
+        let table = dlog_table::<P>(pp, bound);
+        assert_eq!(
+            msg * msg,
+            final_decrypt_with_table::<P>(&table, &pdecs2, 3).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_verify_share() {
+        let pp = paramgen::<P>();
+
+        let (sk, pk) = keygen::<P>(pp);
+        let (sks, commitments) = share_sk_verifiable::<P>(pp, sk, 3, 5).unwrap();
+        assert!(
+            sks.iter()
+                .all(|share| verify_share::<P>(pp, pk, &commitments, *share))
+        );
+
+        let (id, s1, s2) = sks[1];
+        assert!(!verify_share::<P>(pp, pk, &commitments, (id, s1 + F::from(1u64), s2)));
+        assert!(!verify_share::<P>(pp, pk, &commitments, (id, s1, s2 + F::from(1u64))));
+        assert!(!verify_share::<P>(pp, pk, &commitments, (id + 1, s1, s2)));
+        let (_, pk_other) = keygen::<P>(pp);
+        assert!(!verify_share::<P>(pp, pk_other, &commitments, sks[1]));
+    }
+
+    #[test]
+    fn test_partial_decryption_proofs() {
+        let pp = paramgen::<P>();
+
+        let (sk, pk) = keygen::<P>(pp);
+        let (sks, commitments) = share_sk_verifiable::<P>(pp, sk, 3, 5).unwrap();
+        let vks = sks
+            .iter()
+            .map(|sk| verification_key::<P>(&commitments, sk.0))
+            .collect::<Vec<_>>();
+
+        let msg = F::from(ptxt);
+        let ct = mul::<P>(pp, encrypt::<P>(pp, pk, msg), encrypt::<P>(pp, pk, msg));
+        let pdecs = sks
+            .iter()
+            .map(|sk| partial_decrypt_with_proof::<P>(pp, *sk, ct))
+            .collect::<Vec<_>>();
+        for (vk, (pdec, proof)) in vks.iter().zip(pdecs.iter()) {
+            assert!(verify_partial_decryption::<P>(pp, *vk, ct, *pdec, proof));
+        }
+        let (mut bad, proof) = pdecs[0];
+        bad.2 = bad.2 + bad.1;
+        assert!(!verify_partial_decryption::<P>(pp, vks[0], ct, bad, &proof));
+        assert!(!verify_partial_decryption::<P>(pp, vks[1], ct, pdecs[0].0, &proof));
+
+        let inter = intermediate_dec::<P>(
+            pp,
+            &pdecs.iter().map(|(pdec, _)| *pdec).collect::<Vec<_>>(),
+            3,
+        )
+        .unwrap();
+        let pdecs2 = sks
+            .iter()
+            .map(|sk| partial_decrypt2_with_proof::<P>(pp, *sk, inter))
+            .collect::<Vec<_>>();
+        for (vk, (pdec2, proof)) in vks.iter().zip(pdecs2.iter()) {
+            assert!(verify_partial_decryption2::<P>(pp, *vk, inter, *pdec2, proof));
+        }
+        let (mut bad, proof) = pdecs2[0];
+        bad.1 = bad.1 + bad.2;
+        assert!(!verify_partial_decryption2::<P>(pp, vks[0], inter, bad, &proof));
+        assert_eq!(
+            final_decrypt::<P>(
+                pp,
+                &pdecs2.iter().map(|(pdec2, _)| *pdec2).collect::<Vec<_>>(),
+                3,
+                bound
+            )
+            .unwrap(),
+            msg * msg
+        );
+    }
+
+    #[test]
+    fn test_robust_decryption() {
+        let pp = paramgen::<P>();
+
+        let (sk, pk) = keygen::<P>(pp);
+        let (sks, commitments) = share_sk_verifiable::<P>(pp, sk, 3, 6).unwrap();
+        let vks = sks
+            .iter()
+            .map(|sk| verification_key::<P>(&commitments, sk.0))
+            .collect::<Vec<_>>();
+
+        let msg = F::from(ptxt);
+        let ct = mul::<P>(pp, encrypt::<P>(pp, pk, msg), encrypt::<P>(pp, pk, msg));
+        let mut pdecs = sks
+            .iter()
+            .map(|sk| partial_decrypt_with_proof::<P>(pp, *sk, ct))
+            .collect::<Vec<_>>();
+        // Shares 2 and 4 send garbage, share 5 reuses a proof under the wrong id
+        pdecs[1].0.1 = pdecs[1].0.1 + pdecs[1].0.2;
+        pdecs[3].0.3 = pdecs[3].0.2;
+        pdecs[4] = ((5, pdecs[5].0.1, pdecs[5].0.2, pdecs[5].0.3, pdecs[5].0.4), pdecs[5].1);
+        // A valid duplicate of share 1 is ignored
+        pdecs.push(pdecs[0]);
+
+        let (inter, invalid) = robust_intermediate_dec::<P>(pp, &vks, ct, &pdecs, 3).unwrap();
+        assert_eq!(invalid, vec![2, 4, 5]);
+        let mut pdecs2 = sks
+            .iter()
+            .map(|sk| partial_decrypt2_with_proof::<P>(pp, *sk, inter))
+            .collect::<Vec<_>>();
+        pdecs2[0].0.1 = pdecs2[0].0.2;
+        let (pt, invalid) = robust_final_decrypt::<P>(pp, &vks, inter, &pdecs2, 3, bound).unwrap();
+        assert_eq!(pt, msg * msg);
+        assert_eq!(invalid, vec![1]);
+
+        // Unknown share ids are rejected, and too many bad shares leave too few to decrypt
+        assert_eq!(
+            check_partial_decryptions::<P>(pp, &vks[1..], ct, &pdecs[..1]).1,
+            vec![1]
+        );
+        assert_eq!(
+            robust_intermediate_dec::<P>(pp, &vks, ct, &pdecs[1..5], 3).unwrap_err(),
+            ThbgnError::Share(ShareError::BelowThreshold(3, 1))
+        );
+    }
+
+    #[test]
+    fn test_bit_proof() {
+        let pp = paramgen::<P>();
+
+        let (sk, pk) = keygen::<P>(pp);
+        for bit in [false, true] {
+            let (ct, proof) = encrypt_bit_with_proof::<P>(pp, pk, bit, b"ctx");
+            assert!(verify_bit::<P>(pp, pk, &ct, &proof, b"ctx"));
+            assert!(!verify_bit::<P>(pp, pk, &ct, &proof, b"other"));
+            let pt = decrypt::<P>(pp, sk, mul::<P>(pp, ct, ct), bound).unwrap();
+            assert_eq!(pt, F::from(bit as u64));
+        }
+
+        // Mixing the halves of an encryption of 0 and of 1 is caught
+        let (ct0, proof) = encrypt_bit_with_proof::<P>(pp, pk, false, b"");
+        let (ct1, _) = encrypt_bit_with_proof::<P>(pp, pk, true, b"");
+        let mixed = Ciphertext1((ct0.0.0, ct0.0.1, ct1.0.2, ct1.0.3));
+        assert!(!verify_bit::<P>(pp, pk, &mixed, &proof, b""));
+        assert!(!verify_bit::<P>(pp, pk, &ct1, &proof, b""));
+    }
+
+    #[test]
+    fn test_ciphertext_proof() {
+        let pp = paramgen::<P>();
+
+        let (sk, pk) = keygen::<P>(pp);
+        let msg = F::from(ptxt);
+        let (ct, proof) = encrypt_with_proof::<P>(pp, pk, msg);
+        assert!(verify_ciphertext::<P>(pp, pk, &ct, &proof));
+        let pt = decrypt::<P>(pp, sk, mul::<P>(pp, ct, ct), bound).unwrap();
+        assert_eq!(pt, msg * msg);
+
+        let mut bytes = Vec::new();
+        proof.serialize_compressed(&mut bytes).unwrap();
+        assert_eq!(
+            CiphertextProof::<P>::deserialize_compressed(&bytes[..]).unwrap(),
+            proof
+        );
+
+        // Different messages in the two halves
+        let (other, _) = encrypt_with_proof::<P>(pp, pk, msg + F::from(1u64));
+        let mixed = Ciphertext1((ct.0.0, ct.0.1, other.0.2, other.0.3));
+        assert!(!verify_ciphertext::<P>(pp, pk, &mixed, &proof));
+        assert!(!verify_ciphertext::<P>(pp, pk, &other, &proof));
+    }
+
+    #[test]
+    fn test_range_proof() {
+        let pp = paramgen::<P>();
+
+        let (sk, pk) = keygen::<P>(pp);
+        for msg in [0, 5, 15] {
+            let (ct, proof) = encrypt_with_range_proof::<P>(pp, pk, msg, 4, b"ctx").unwrap();
+            assert!(verify_range::<P>(pp, pk, &ct, 4, &proof, b"ctx"));
+            assert!(!verify_range::<P>(pp, pk, &ct, 3, &proof, b"ctx"));
+            // Replayed in another context, e.g. by another recipient or in another period
+            assert!(!verify_range::<P>(pp, pk, &ct, 4, &proof, b"other"));
+            let one = encrypt::<P>(pp, pk, F::from(1u64));
+            let pt = decrypt::<P>(pp, sk, mul::<P>(pp, ct, one), bound).unwrap();
+            assert_eq!(pt, F::from(msg));
+
+            let mut bytes = Vec::new();
+            proof.serialize_compressed(&mut bytes).unwrap();
+            assert_eq!(
+                RangeProof::<P>::deserialize_compressed(&bytes[..]).unwrap(),
+                proof
+            );
+        }
+        assert_eq!(
+            encrypt_with_range_proof::<P>(pp, pk, 16, 4, b"ctx").unwrap_err(),
+            ThbgnError::OutOfRange(16, 4)
+        );
+
+        // The proof does not transfer to another ciphertext
+        let (ct, proof) = encrypt_with_range_proof::<P>(pp, pk, 3, 4, b"ctx").unwrap();
+        let ct2 = add::<P>(pp, ct, ct);
+        assert!(!verify_range::<P>(pp, pk, &ct2, 4, &proof, b"ctx"));
+    }
+
+    #[test]
+    fn test_helper_output() {
+        let pp = paramgen::<P>();
+
+        let (sk, pk) = keygen::<P>(pp);
+        let inputs = (0..3)
+            .map(|i| {
+                vec![
+                    encrypt::<P>(pp, pk, F::from((i % 2) as u64)),
+                    encrypt::<P>(pp, pk, F::from(ptxt)),
+                ]
+            })
+            .collect::<Vec<_>>();
+        let output = evaluate_helper::<P>(pp, 3, &inputs);
+        assert!(verify_helper_output::<P>(pp, &inputs, &output));
+        let pt = decrypt::<P>(pp, sk, output.output[1][0], bound).unwrap();
+        assert_eq!(pt, F::from(ptxt));
+
+        let mut swapped = inputs.clone();
+        swapped.swap(0, 1);
+        assert!(!verify_helper_output::<P>(pp, &swapped, &output));
+        let mut other = output.clone();
+        other.output[0][0] = other.output[1][0];
+        assert!(!verify_helper_output::<P>(pp, &inputs, &other));
+
+        // Moving a product to another list changes the signed bytes
+        let mut reshaped = output.clone();
+        let ct = reshaped.output[1].pop().unwrap();
+        reshaped.output[0].push(ct);
+        assert_ne!(reshaped.to_bytes(), output.to_bytes());
+    }
+
+    #[test]
+    fn test_rerandomize() {
+        let pp = paramgen::<P>();
+
+        let (sk, pk) = keygen::<P>(pp);
+        let pk_t = pk_t::<P>(pp, pk);
+
+        let msg = F::from(ptxt);
+        let ct0 = encrypt::<P>(pp, pk, msg);
+        let ct1 = rerandomize::<P>(pp, pk, ct0);
+        assert_ne!(ct0, ct1);
+        let ct2 = add_rerandomized::<P>(pp, pk, ct0, ct1);
+        assert_ne!(ct2, add::<P>(pp, ct0, ct1));
+
+        let ct3 = mul::<P>(pp, ct1, ct2);
+        let ct4 = rerandomize_t::<P>(pk_t, ct3);
+        assert_ne!(ct3, ct4);
+        assert_eq!(
+            msg * msg * F::from(2u64),
+            decrypt::<P>(pp, sk, ct4, 4 * bound).unwrap()
+        );
+        let ct5 = mul_rerandomized::<P>(pp, pk_t, ct0, ct2);
+        assert_eq!(
+            msg * msg * F::from(2u64),
+            decrypt::<P>(pp, sk, ct5, 4 * bound).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_level2_arithmetic() {
+        let pp = paramgen::<P>();
+
+        let (sk, pk) = keygen::<P>(pp);
+        let pk_t = pk_t::<P>(pp, pk);
+        let sks = share_sk::<P>(sk, 3, 5).unwrap();
+
+        // Sum of bit_i * data_i, computed in GT
+        let bits = [1u64, 0, 1, 1];
+        let data = [2u64, 5, 3, 1];
+        let ct = bits
+            .iter()
+            .zip(data.iter())
+            .map(|(b, d)| {
+                mul::<P>(
+                    pp,
+                    encrypt::<P>(pp, pk, F::from(*b)),
+                    encrypt::<P>(pp, pk, F::from(*d)),
+                )
+            })
+            .fold(encrypt_t::<P>(pk_t, F::zero()), add_t::<P>);
+        assert_eq!(F::from(6u64), decrypt::<P>(pp, sk, ct, bound).unwrap());
+
+        // 2 * (6 + 3) - 4 - 5 = 9
+        let ct = add_t::<P>(ct, lift::<P>(pp, encrypt::<P>(pp, pk, F::from(3u64))));
+        let ct = scalar_mul_t::<P>(ct, F::from(2u64));
+        let ct = sub_t::<P>(ct, encrypt_t::<P>(pk_t, F::from(4u64)));
+        let ct = sub_t::<P>(ct, lift::<P>(pp, encrypt::<P>(pp, pk, F::from(5u64))));
+        assert_eq!(F::from(9u64), decrypt::<P>(pp, sk, ct, bound).unwrap());
+
+        let pdecs = sks
+            .iter()
+            .map(|sk| partial_decrypt::<P>(pp, *sk, ct))
+            .collect::<Vec<_>>();
+        let inter = intermediate_dec::<P>(pp, &pdecs, 3).unwrap();
+        let pdecs2 = sks
+            .iter()
+            .map(|sk| partial_decrypt2::<P>(pp, *sk, inter))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            F::from(9u64),
+            final_decrypt::<P>(pp, &pdecs2, 3, bound).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_1round_distributeddec() {
+        let pp = paramgen::<P>();
+
+        let (sk, pk) = keygen::<P>(pp);
+        let sks = share_sk_1round::<P>(sk, 3, 5).unwrap();
+
+        let msg = F::from(ptxt);
+        let ct = mul::<P>(pp, encrypt::<P>(pp, pk, msg), encrypt::<P>(pp, pk, msg));
+
+        // Single round, with any 3 of the 5 shares
+        let pdecs1 = sks
+            .iter()
+            .map(|sk| partial_decrypt_1round::<P>(pp, *sk, ct))
+            .collect::<Vec<_>>();
+        let pt = final_decrypt_1round::<P>(pp, ct, &pdecs1[2..], 3, bound).unwrap();
+        assert_eq!(msg * msg, pt);
+        let one_round = final_decrypt_1round_in_exp::<P>(ct, &pdecs1[..3], 3).unwrap();
+        assert_eq!(
+            one_round,
+            final_decrypt_1round_in_exp::<P>(ct, &pdecs1[2..], 3).unwrap()
+        );
+
+        // Two rounds, with the (s1, s2) part of the same shares
+        let sks2 = sks
+            .iter()
+            .map(|(id, s1, s2, _)| (*id, *s1, *s2))
+            .collect::<Vec<_>>();
+        let pdecs = sks2
+            .iter()
+            .map(|sk| partial_decrypt::<P>(pp, *sk, ct))
+            .collect::<Vec<_>>();
+        let inter = intermediate_dec::<P>(pp, &pdecs, 3).unwrap();
+        let pdecs2 = sks2
+            .iter()
+            .map(|sk| partial_decrypt2::<P>(pp, *sk, inter))
+            .collect::<Vec<_>>();
+        assert_eq!(final_decrypt_in_exp::<P>(&pdecs2, 3).unwrap(), one_round);
+        assert_eq!(final_decrypt::<P>(pp, &pdecs2, 3, bound).unwrap(), pt);
+    }
+
+    #[test]
+    fn test_batch() {
+        let pp = paramgen::<P>();
+
+        let (sk, pk) = keygen::<P>(pp);
+        let tables = encryption_tables::<P>(pp, pk, 4);
+        let msgs = (0..4u64).map(F::from).collect::<Vec<_>>();
+        let cts = encrypt_batch::<P>(&tables, &msgs);
+        let table = dlog_table::<P>(pp, bound);
+        for (msg, ct) in msgs.iter().zip(cts.iter()) {
+            let ct = lift::<P>(pp, *ct);
+            assert_eq!(*msg, decrypt_with_table::<P>(&table, sk, ct).unwrap());
+        }
+
+        // Reconstruction in G1 and GT
+        let sks = share_sk::<P>(sk, 3, 5).unwrap();
+        let shares = sks
+            .iter()
+            .map(|(id, s1, _)| (*id, pp.0 * s1))
+            .collect::<Vec<_>>();
+        assert_eq!(pk.0, reconstruct_secret_in_exp(&shares[1..4]).unwrap());
+        let gT = P::pairing(pp.0, pp.1);
+        let shares = sks
+            .iter()
+            .map(|(id, _, s2)| (*id, gT * s2))
+            .collect::<Vec<_>>();
+        assert_eq!(gT * sk.1, reconstruct_secret_in_exp(&shares[..3]).unwrap());
+    }
+
+    #[test]
+    fn test_inner_product() {
+        let pp = paramgen::<P>();
+
+        let (sk, pk) = keygen::<P>(pp);
+        let bits = [1u64, 0, 1, 1];
+        let data = [2u64, 5, 3, 1];
+        let cts_a = bits
+            .iter()
+            .map(|b| encrypt::<P>(pp, pk, F::from(*b)))
+            .collect::<Vec<_>>();
+        let cts_b = data
+            .iter()
+            .map(|d| encrypt::<P>(pp, pk, F::from(*d)))
+            .collect::<Vec<_>>();
+
+        let ct = inner_product::<P>(pp, &cts_a, &cts_b).unwrap();
+        let expected = cts_a
+            .iter()
+            .zip(cts_b.iter())
+            .map(|(a, b)| mul::<P>(pp, *a, *b))
+            .reduce(add_t::<P>)
+            .unwrap();
+        assert_eq!(expected, ct);
+        assert_eq!(F::from(6u64), decrypt::<P>(pp, sk, ct, bound).unwrap());
+        assert_eq!(
+            inner_product::<P>(pp, &cts_a, &cts_b[1..]),
+            Err(ThbgnError::LengthMismatch(4, 3))
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let pp = paramgen::<P>();
+
+        let (sk, pk) = keygen::<P>(pp);
+        assert_eq!(
+            share_sk::<P>(sk, 6, 5).unwrap_err(),
+            ThbgnError::Share(ShareError::InvalidThreshold(6, 5))
+        );
+        let sks = share_sk::<P>(sk, 3, 5).unwrap();
+
+        let msg = F::from(bound);
+        let ct = mul::<P>(pp, encrypt::<P>(pp, pk, msg), encrypt::<P>(pp, pk, msg));
+        assert_eq!(
+            decrypt::<P>(pp, sk, ct, bound).unwrap_err(),
+            ThbgnError::DlogOutOfBound(bound)
+        );
+
+        let pdecs = sks
+            .iter()
+            .map(|sk| partial_decrypt::<P>(pp, *sk, ct))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            intermediate_dec::<P>(pp, &[], 3).unwrap_err(),
+            ThbgnError::Share(ShareError::BelowThreshold(3, 0))
+        );
+        assert_eq!(
+            intermediate_dec::<P>(pp, &pdecs[..2], 3).unwrap_err(),
+            ThbgnError::Share(ShareError::BelowThreshold(3, 2))
+        );
+        let duplicated = [pdecs[0], pdecs[1], pdecs[0]];
+        assert_eq!(
+            intermediate_dec::<P>(pp, &duplicated, 3).unwrap_err(),
+            ThbgnError::Share(ShareError::DuplicateShareId(pdecs[0].0))
+        );
+        let mut inconsistent = pdecs.clone();
+        inconsistent[4].4 = inconsistent[4].4 + inconsistent[4].1;
+        assert_eq!(
+            intermediate_dec::<P>(pp, &inconsistent, 3).unwrap_err(),
+            ThbgnError::InconsistentC4(pdecs[4].0)
+        );
+
+        let bytes = ct.to_bytes();
+        assert_eq!(
+            CiphertextT::<P>::from_bytes(&bytes[1..]).unwrap_err(),
+            ThbgnError::MalformedEncoding
+        );
+        assert_eq!(CiphertextT::<P>::from_bytes(&bytes).unwrap(), ct);
     }
 }
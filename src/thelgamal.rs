@@ -1,257 +1,516 @@
-use ark_ec::{pairing, Group};
-use ark_ec::pairing::*;
-use ark_std::Zero;
-// use ark_ff::AdditiveGroup;
-use ark_ff::Field;
+use ark_ec::Group;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
 use ark_std::UniformRand;
-use bincode::de;
-use rand::rngs::ThreadRng;
 use rand::thread_rng;
-use secret_sharing_and_dkg::shamir_ss::*;
-use secret_sharing_and_dkg::common::ShareId;
+use secret_sharing_and_dkg::common::{ShareId, lagrange_basis_at_0_for_all};
+use secret_sharing_and_dkg::shamir_ss::deal_secret;
 
-type PublicParameters<G: Group> = G;
-type SecretKey<G: Group> = G::ScalarField;
+use crate::dlog::BabyStepTable;
+use crate::proofs::{self, BitProof, DleqProof, ElGamalInstance, Transcript, prove_bit};
+use crate::thbgn::rand_invertible;
+use crate::vss::{self, ShareError};
 
-type PublicKey<G: Group> = G;
-struct Ciphertext<G: Group>(G, G);
+pub type PublicParameters<G: Group> = G;
+pub type SecretKey<G: Group> = G::ScalarField;
+pub type SecretKeyShare<G: Group> = (ShareId, G::ScalarField);
 
-type PartialDecryption<G: Group> = (
-   G
-);
+pub type PublicKey<G: Group> = G;
 
-pub fn rand_invertible<F: Field>() -> F {
-    let mut rng = thread_rng();
-    loop {
-        let candidate = F::rand(&mut rng);
-        if candidate.inverse().is_some() {
-            return candidate;
+/// Exponential ElGamal ciphertext (g^r, g^m h^r)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Ciphertext<G: Group>(pub (G, G));
+
+pub type PartialDecryption<G: Group> = (ShareId, G);
+/// Verification key (share id, s_i g) of a key share
+pub type VerificationKey<G: Group> = (ShareId, G);
+/// Proof that a partial decryption used the s_i of a verification key
+pub type PartialDecryptionProof<G: Group> = DleqProof<G::ScalarField>;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ThelgamalError {
+    /// The plaintext is not in (-bound, bound)
+    DlogOutOfBound(u64),
+    /// Invalid share ids or threshold
+    Share(ShareError),
+    /// Undecodable bytes
+    MalformedEncoding,
+}
+
+impl std::fmt::Display for ThelgamalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ThelgamalError::DlogOutOfBound(bound) => {
+                write!(f, "discrete log not found in (-{}, {})", bound, bound)
+            }
+            ThelgamalError::Share(err) => err.fmt(f),
+            ThelgamalError::MalformedEncoding => write!(f, "malformed encoding"),
         }
     }
 }
 
+impl std::error::Error for ThelgamalError {}
+
+impl From<ShareError> for ThelgamalError {
+    fn from(err: ShareError) -> Self {
+        ThelgamalError::Share(err)
+    }
+}
+
+impl From<SerializationError> for ThelgamalError {
+    fn from(_: SerializationError) -> Self {
+        ThelgamalError::MalformedEncoding
+    }
+}
+
+impl<G: Group> Ciphertext<G> {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.0.serialize_compressed(&mut bytes).unwrap();
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ThelgamalError> {
+        let mut reader = bytes;
+        let ct = <(G, G)>::deserialize_compressed(&mut reader)?;
+        if !reader.is_empty() {
+            return Err(ThelgamalError::MalformedEncoding);
+        }
+        Ok(Ciphertext(ct))
+    }
+}
+
 pub fn paramgen<G: Group>() -> PublicParameters<G> {
     let mut rng = thread_rng();
     G::rand(&mut rng)
 }
 
-pub fn keygen<P: Pairing>(pp: PublicParameters<P>) -> (SecretKey<P>, PublicKey<P>) {
-    let s: P::ScalarField = rand_invertible();
-    (s, s * pp)
+pub fn keygen<G: Group>(pp: PublicParameters<G>) -> (SecretKey<G>, PublicKey<G>) {
+    let s: G::ScalarField = rand_invertible();
+    (s, pp * s)
 }
 
-pub fn encrypt<P: Pairing>(
-    pp: PublicParameters<P>,
-    pk: PublicKey<P>,
-    msg: P::ScalarField,
-) -> Ciphertext1<P> {
+pub fn encrypt<G: Group>(
+    pp: PublicParameters<G>,
+    pk: PublicKey<G>,
+    msg: G::ScalarField,
+) -> Ciphertext<G> {
     let mut rng = thread_rng();
-    let r = P::ScalarField::rand(&mut rng);
+    let r = G::ScalarField::rand(&mut rng);
+    encrypt_with_randomness(pp, pk, msg, r)
+}
+
+pub(crate) fn encrypt_with_randomness<G: Group>(
+    pp: PublicParameters<G>,
+    pk: PublicKey<G>,
+    msg: G::ScalarField,
+    r: G::ScalarField,
+) -> Ciphertext<G> {
     let g = pp;
     let h = pk;
-    Ciphertext((
-        g * r,
-        msg + h * r,
-    ))
-}
-
-pub fn add<P: Pairing>(
-    pp: PublicParameters<P>,
-    ct1: Ciphertext1<P>,
-    ct2: Ciphertext1<P>,
-) -> Ciphertext1<P> {
-    let (c1_1, c1_2, c1_3, c1_4) = ct1.0;
-    let (c2_1, c2_2, c2_3, c2_4) = ct2.0;
-    Ciphertext1((c1_1 + c2_1, c1_2 + c2_2, c1_3 + c2_3, c1_4 + c2_4))
-    // TODO: rerandomize
-}
-
-pub fn mul<P: Pairing>(
-    pp: PublicParameters<P>,
-    ct1: Ciphertext1<P>,
-    ct2: Ciphertext1<P>,
-) -> CiphertextT<P> {
-    let (c1_1, c1_2, c1_3, c1_4) = ct1.0;
-    let (c2_1, c2_2, c2_3, c2_4) = ct2.0;
-    CiphertextT((
-        P::pairing(c1_1, c2_3),
-        P::pairing(c1_1, c2_4),
-        P::pairing(c1_2, c2_3),
-        P::pairing(c1_2, c2_4),
-    ))
-}
-
-fn find_dlog<P: Pairing>(
-    base: PairingOutput<P>,
-    p: PairingOutput<P>,
-    bound: P::ScalarField,
-) -> P::ScalarField 
-where P::ScalarField: ark_ff::PrimeField
-{
-    // Find discrete log of p in base
-    // Enumerate from -Bound to Bound in F, test if base^i = p
-
-    let mut i = P::ScalarField::zero();
-    while i < bound {
-        if base * i == p {
-            return i;
-        }
-        if base * i == -p {
-            return -i;
-        }
-        i += P::ScalarField::ONE;
-    }
-    panic!("Discrete log not found");
+    Ciphertext((g * r, g * msg + h * r))
 }
 
-pub fn decrypt<P: Pairing>(
-    pp: PublicParameters<P>,
-    sk: SecretKey<P>,
-    ct: CiphertextT<P>,
-    bound: u64,
-) -> P::ScalarField {
-    let (s1, s2) = sk;
-    let (c1, c2, c3, c4) = ct.0;
-    let (g1, g2) = pp;
-    let gT = P::pairing(g1, g2);
-    find_dlog(
-        gT,
-        c1 * (s1 * s2) - c2 * s1 - c3 * s2 + c4,
-        P::ScalarField::from(bound),
-    )
-}
-
-pub fn share_sk<P: Pairing>(sk: SecretKey<P>, threshold: usize, total: usize) -> Vec<SecretKey<P>>
-where
-    <P as ark_ec::pairing::Pairing>::ScalarField: ark_ff::PrimeField
-{
+const BIT_LABEL: &[u8] = b"thelgamal bit";
+
+/// Label of a bit proof made in `context`
+fn bit_label(context: &[u8]) -> Vec<u8> {
+    [BIT_LABEL, context].concat()
+}
+
+/// Encrypt a bit together with a proof that the ciphertext encrypts 0 or 1. The proof is bound to
+/// `context`, e.g. the period and tag of the submission it belongs to, and only verifies in the
+/// same context.
+pub fn encrypt_bit_with_proof<G: Group>(
+    pp: PublicParameters<G>,
+    pk: PublicKey<G>,
+    bit: bool,
+    context: &[u8],
+) -> (Ciphertext<G>, BitProof<G::ScalarField>) {
     let mut rng = thread_rng();
+    let r = G::ScalarField::rand(&mut rng);
+    let ct = encrypt_with_randomness(pp, pk, G::ScalarField::from(bit as u64), r);
+    let instance = [bit_instance(pp, pk, &ct)];
+    let proof = prove_bit::<G, G>(&bit_label(context), &instance, &[], bit, &[r]);
+    (ct, proof)
+}
+
+/// Check a proof made in `context` that `ct` encrypts 0 or 1
+pub fn verify_bit<G: Group>(
+    pp: PublicParameters<G>,
+    pk: PublicKey<G>,
+    ct: &Ciphertext<G>,
+    proof: &BitProof<G::ScalarField>,
+    context: &[u8],
+) -> bool {
+    let instance = [bit_instance(pp, pk, ct)];
+    proofs::verify_bit::<G, G>(&bit_label(context), &instance, &[], proof)
+}
+
+pub(crate) fn bit_instance<G: Group>(
+    pp: PublicParameters<G>,
+    pk: PublicKey<G>,
+    ct: &Ciphertext<G>,
+) -> ElGamalInstance<G> {
+    (pp, pk, ct.0.0, ct.0.1)
+}
+
+pub fn add<G: Group>(ct1: Ciphertext<G>, ct2: Ciphertext<G>) -> Ciphertext<G> {
+    let (c1_1, c1_2) = ct1.0;
+    let (c2_1, c2_2) = ct2.0;
+    Ciphertext((c1_1 + c2_1, c1_2 + c2_2))
+}
 
-    let (shares1, poly1) = deal_secret(&mut rng, sk.0, threshold as ShareId, total as ShareId).unwrap();
-    let (shares2, poly2) = deal_secret(&mut rng, sk.1, threshold as ShareId, total as ShareId).unwrap();
-    // let (shares1, poly1) =
-    //     deal_secret::<ThreadRng, <P as ark_ec::pairing::Pairing>::ScalarField>>(&mut rng, sk.0, threshold as ShareID, total as ShareID).unwrap();
-    // let (shares2, poly2) =
-    //     deal_secret::<ThreadRng, <P as ark_ec::pairing::Pairing>::ScalarField>>(&mut rng, sk.1, threshold as ShareID, total as ShareID).unwrap();
+pub fn sub<G: Group>(ct1: Ciphertext<G>, ct2: Ciphertext<G>) -> Ciphertext<G> {
+    let (c1_1, c1_2) = ct1.0;
+    let (c2_1, c2_2) = ct2.0;
+    Ciphertext((c1_1 - c2_1, c1_2 - c2_2))
+}
 
-    shares1.0.into_iter().zip(shares2.0.into_iter()).map(|(s1, s2)| (s1.share, s2.share)).collect()
+pub fn scalar_mul<G: Group>(ct: Ciphertext<G>, k: G::ScalarField) -> Ciphertext<G> {
+    let (c1, c2) = ct.0;
+    Ciphertext((c1 * k, c2 * k))
 }
 
-pub fn partial_decrypt<P: Pairing>(
-    pp: PublicParameters<P>,
-    sk: SecretKey<P>,
-    ct: CiphertextT<P>,
-) -> PartialDecryption<P> {
-    let (c1, c2, c3, c4) = ct.0;
-    let (s1, s2) = sk;
-    (c1 * s1, c2 * s1, c3 * s2, c4)
+/// Baby-step table for discrete logarithms in base g
+pub fn dlog_table<G: Group>(pp: PublicParameters<G>, bound: u64) -> BabyStepTable<G> {
+    BabyStepTable::new(pp, bound)
 }
 
-pub fn intermediate_dec<P: Pairing>(
-    pp: PublicParameters<P>,
-    pdecs: &[PartialDecryption<P>],
+pub fn decrypt<G: Group>(
+    pp: PublicParameters<G>,
+    sk: SecretKey<G>,
+    ct: Ciphertext<G>,
     bound: u64,
-) -> PDecFinal1<P> {
-    // Reconstruct s1*c1, c2*s1, c3*s2, c4
-    let (s1c1, c2s2, c3s2, c4) = pdecs.iter().fold(
-        (PairingOutput::<P>::zero(), PairingOutput::<P>::zero(),PairingOutput::<P>::zero(), PairingOutput::<P>::zero()),
-        |acc, pdec| {
-            let (c1, c2, c3, c4) = pdec;
-            (acc.0 + c1, acc.1 + c2, acc.2 + c3, acc.3 + c4)
-        },
-    );
-    (s1c1, -c2s2 - c3s2 + c4)
-}
-
-pub type PDecFinal1<P> = (PairingOutput<P>, PairingOutput<P>);
-
-pub fn partial_decrypt2<P: Pairing>(
-    pp: PublicParameters<P>,
-    sk: SecretKey<P>,
-    pdec: PDecFinal1<P>,
-) -> PartialDecryption2<P> {
-    let (s1, s2) = sk;
-    let (s1c1, c) = pdec;
-    (s1c1 * s2, c)
-}
-
-pub fn final_decrypt<P: Pairing>(
-    pp: PublicParameters<P>,
-    pdecs: &[PartialDecryption2<P>],
+) -> Result<G::ScalarField, ThelgamalError> {
+    decrypt_with_table(&dlog_table(pp, bound), sk, ct)
+}
+
+/// Same as `decrypt`, with a table precomputed by `dlog_table` for the same public parameters.
+pub fn decrypt_with_table<G: Group>(
+    table: &BabyStepTable<G>,
+    sk: SecretKey<G>,
+    ct: Ciphertext<G>,
+) -> Result<G::ScalarField, ThelgamalError> {
+    let (c1, c2) = ct.0;
+    table
+        .solve(c2 - c1 * sk)
+        .ok_or(ThelgamalError::DlogOutOfBound(table.bound()))
+}
+
+pub fn share_sk<G: Group>(
+    sk: SecretKey<G>,
+    threshold: usize,
+    total: usize,
+) -> Result<Vec<SecretKeyShare<G>>, ThelgamalError> {
+    if threshold == 0 || threshold > total || total > ShareId::MAX as usize {
+        return Err(ShareError::InvalidThreshold(threshold, total).into());
+    }
+    let mut rng = thread_rng();
+
+    let (shares, _poly) = deal_secret(&mut rng, sk, threshold as ShareId, total as ShareId)
+        .map_err(|_| ShareError::InvalidThreshold(threshold, total))?;
+    Ok(shares.0.into_iter().map(|s| (s.id, s.share)).collect())
+}
+
+pub fn partial_decrypt<G: Group>(
+    pp: PublicParameters<G>,
+    sk: SecretKeyShare<G>,
+    ct: Ciphertext<G>,
+) -> PartialDecryption<G> {
+    let (c1, _) = ct.0;
+    let (id, s) = sk;
+    (id, c1 * s)
+}
+
+/// Verification key of `sk`, published by the dealer with the public key
+pub fn verification_key<G: Group>(
+    pp: PublicParameters<G>,
+    sk: SecretKeyShare<G>,
+) -> VerificationKey<G> {
+    let (id, s) = sk;
+    (id, pp * s)
+}
+
+const PDEC_LABEL: &[u8] = b"thelgamal partial_decrypt";
+
+/// Same as `partial_decrypt`, with a proof that the same s_i as in the verification key was used
+pub fn partial_decrypt_with_proof<G: Group>(
+    pp: PublicParameters<G>,
+    sk: SecretKeyShare<G>,
+    ct: Ciphertext<G>,
+) -> (PartialDecryption<G>, PartialDecryptionProof<G>) {
+    let (c1, _) = ct.0;
+    let (_, s) = sk;
+    let pdec = partial_decrypt::<G>(pp, sk, ct);
+    let proof = proofs::prove_dleq(PDEC_LABEL, (pp, pp * s), &[(c1, pdec.1)], s);
+    (pdec, proof)
+}
+
+pub fn verify_partial_decryption<G: Group>(
+    pp: PublicParameters<G>,
+    vk: VerificationKey<G>,
+    ct: Ciphertext<G>,
+    pdec: PartialDecryption<G>,
+    proof: &PartialDecryptionProof<G>,
+) -> bool {
+    let (c1, _) = ct.0;
+    let (id, vk) = vk;
+    let (pdec_id, d) = pdec;
+    id == pdec_id && proofs::verify_dleq(PDEC_LABEL, (pp, vk), &[(c1, d)], proof)
+}
+
+fn final_decrypt_in_exp<G: Group>(
+    ct: Ciphertext<G>,
+    pdecs: &[PartialDecryption<G>],
+    threshold: usize,
+) -> Result<G, ThelgamalError> {
+    vss::check_share_ids(pdecs.iter().map(|pdec| pdec.0), threshold)?;
+    let c1s = reconstruct_secret_in_exp(&pdecs[..threshold])?;
+    Ok(ct.0.1 - c1s)
+}
+
+pub fn final_decrypt<G: Group>(
+    pp: PublicParameters<G>,
+    ct: Ciphertext<G>,
+    pdecs: &[PartialDecryption<G>],
+    threshold: usize,
     bound: u64,
-) -> P::ScalarField {
-    let (s1s2c1, c) = pdecs.iter().fold((PairingOutput::<P>::zero(), PairingOutput::<P>::zero()), |acc, pdec| {
-        let (c1, c2) = pdec;
-        (acc.0 + c1, acc.1 + c2)
-    });
-    find_dlog(
-        P::pairing(pp.0, pp.1),
-        s1s2c1 + c,
-        P::ScalarField::from(bound),
-    )
-}
-
-// /// Given ((i_j, s_i_j G))_{j\in[t]}, output s G where s_i_j = f(i_j) and s = f(0)
-// pub fn reconstruct_secret_in_exp<P:Pairing>(shares:&[(ShareId, PairingOutput<P>)]) -> Result<PairingOutput<>, SSError> {
-//     // let threshold = self.threshold();
-//     // let len = self.0.len() as ShareId;
-//     // if threshold > len {
-//     //     return Err(SSError::BelowThreshold(threshold, len));
-//     // }
-//     // let shares = &self.0[0..threshold as usize];
-//     // let share_ids = shares.iter().map(|s| s.0).collect::<Vec<_>>();
-//     // let basis = common::lagrange_basis_at_0_for_all::<F>(share_ids)?;
-//     // Ok(cfg_into_iter!(basis)
-//     //     .zip(cfg_into_iter!(shares))
-//     //     .map(|(b, s)| b * s.1)
-//     //     .sum::<PairingOutput<P>>())
-//     todo!()
-// }
+) -> Result<G::ScalarField, ThelgamalError> {
+    final_decrypt_with_table(&dlog_table(pp, bound), ct, pdecs, threshold)
+}
+
+/// Same as `final_decrypt`, with a table precomputed by `dlog_table` for the same public
+/// parameters.
+pub fn final_decrypt_with_table<G: Group>(
+    table: &BabyStepTable<G>,
+    ct: Ciphertext<G>,
+    pdecs: &[PartialDecryption<G>],
+    threshold: usize,
+) -> Result<G::ScalarField, ThelgamalError> {
+    table
+        .solve(final_decrypt_in_exp(ct, pdecs, threshold)?)
+        .ok_or(ThelgamalError::DlogOutOfBound(table.bound()))
+}
+
+/// Given ((i_j, s_i_j G))_{j\in[t]}, output s G where s_i_j = f(i_j) and s = f(0)
+pub fn reconstruct_secret_in_exp<G: Group>(shares: &[(ShareId, G)]) -> Result<G, ThelgamalError> {
+    vss::check_share_ids(shares.iter().map(|s| s.0), 1)?;
+    let share_ids = shares.iter().map(|s| s.0).collect::<Vec<_>>();
+    let basis = lagrange_basis_at_0_for_all::<G::ScalarField>(share_ids)
+        .map_err(|_| ThelgamalError::MalformedEncoding)?;
+    Ok(basis
+        .into_iter()
+        .zip(shares.iter())
+        .map(|(b, s)| s.1 * b)
+        .sum::<G>())
+}
+
+/// Output of the helper for a period: the sum of its input ciphertexts, with a digest of the
+/// input list so that anyone given the list can recompute the sum.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HelperOutput<G: Group> {
+    pub period: u16,
+    pub inputs_digest: [u8; 32],
+    pub output: Ciphertext<G>,
+}
+
+impl<G: Group> HelperOutput<G> {
+    /// Bytes signed by the helper
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.period.to_be_bytes().to_vec();
+        bytes.extend_from_slice(&self.inputs_digest);
+        bytes.extend_from_slice(&self.output.to_bytes());
+        bytes
+    }
+}
+
+pub fn inputs_digest<G: Group>(period: u16, inputs: &[Ciphertext<G>]) -> [u8; 32] {
+    let mut transcript = Transcript::new(b"thelgamal helper inputs");
+    transcript.append_bytes(&period.to_be_bytes());
+    transcript.append_bytes(&(inputs.len() as u64).to_be_bytes());
+    inputs.iter().for_each(|ct| transcript.append(&ct.0));
+    transcript.digest()
+}
+
+/// f1: sum of all input ciphertexts
+pub fn evaluate_helper<G: Group>(period: u16, inputs: &[Ciphertext<G>]) -> HelperOutput<G> {
+    HelperOutput {
+        period,
+        inputs_digest: inputs_digest(period, inputs),
+        output: inputs
+            .iter()
+            .fold(Ciphertext((G::zero(), G::zero())), |acc, ct| add(acc, *ct)),
+    }
+}
+
+/// Check that `output` is the sum of `inputs`, and that `inputs` is the list the helper committed
+/// to
+pub fn verify_helper_output<G: Group>(inputs: &[Ciphertext<G>], output: &HelperOutput<G>) -> bool {
+    evaluate_helper(output.period, inputs) == *output
+}
 
 #[cfg(test)]
 mod test {
-    type P = ark_bls12_381::Bls12_381;
-    type F = <P as Pairing>::ScalarField;
+    type G = <ark_bls12_381::Bls12_381 as ark_ec::pairing::Pairing>::G1;
+    type F = <G as Group>::ScalarField;
+    const PTXT: u64 = 3;
+    const BOUND: u64 = 1 << 4;
 
     use super::*;
     #[test]
     fn test_enc_dec() {
-        let pp = paramgen::<P>();
+        let pp = paramgen::<G>();
 
-        let (sk, pk) = keygen::<P>(pp);
+        let (sk, pk) = keygen::<G>(pp);
 
-        let msg = F::from(3);
-        let ct0 = encrypt::<P>(pp, pk, msg);
-        let ct1 = encrypt::<P>(pp, pk, msg);
-        let ct2 = mul::<P>(pp, ct0, ct1);
-        let pt = decrypt::<P>(pp, sk, ct2, (1 << 4));
-        assert_eq!(msg * msg, pt);
+        let msg = F::from(PTXT);
+        let ct0 = encrypt::<G>(pp, pk, msg);
+        let ct1 = encrypt::<G>(pp, pk, F::from(1u64));
+        let ct2 = sub::<G>(scalar_mul::<G>(add::<G>(ct0, ct0), F::from(2u64)), ct1);
+        let pt = decrypt::<G>(pp, sk, ct2, BOUND).unwrap();
+        assert_eq!(F::from(4 * PTXT - 1), pt);
+        let pt = decrypt::<G>(pp, sk, sub::<G>(ct1, ct0), BOUND).unwrap();
+        assert_eq!(F::from(1u64) - msg, pt);
+
+        let ct3 = scalar_mul::<G>(ct0, F::from(BOUND));
+        assert_eq!(
+            decrypt::<G>(pp, sk, ct3, BOUND).unwrap_err(),
+            ThelgamalError::DlogOutOfBound(BOUND)
+        );
+        assert_eq!(Ciphertext::<G>::from_bytes(&ct3.to_bytes()).unwrap(), ct3);
     }
 
     #[test]
     fn test_enc_distributeddec() {
-        let pp = paramgen::<P>();
+        let pp = paramgen::<G>();
 
-        let (sk, pk) = keygen::<P>(pp);
-        let sks = share_sk::<P>(sk, 3, 5);
+        let (sk, pk) = keygen::<G>(pp);
+        let sks = share_sk::<G>(sk, 3, 5).unwrap();
 
-        let msg = F::from(3);
-        let ct0 = encrypt::<P>(pp, pk, msg);
-        let ct1 = encrypt::<P>(pp, pk, msg);
-        let ct2 = mul::<P>(pp, ct0, ct1);
+        let msg = F::from(PTXT);
+        let ct = add::<G>(encrypt::<G>(pp, pk, msg), encrypt::<G>(pp, pk, msg));
 
         let pdecs = sks
             .iter()
-            .map(|sk| partial_decrypt::<P>(pp, *sk, ct2))
+            .map(|sk| partial_decrypt::<G>(pp, *sk, ct))
+            .collect::<Vec<_>>();
+        let pt = final_decrypt::<G>(pp, ct, &pdecs, 3, BOUND).unwrap();
+        assert_eq!(msg + msg, pt);
+
+        let table = dlog_table::<G>(pp, BOUND);
+        let pt = final_decrypt_with_table::<G>(&table, ct, &pdecs[2..], 3).unwrap();
+        assert_eq!(msg + msg, pt);
+        assert_eq!(
+            final_decrypt_with_table::<G>(&table, ct, &pdecs[3..], 3).unwrap_err(),
+            ThelgamalError::Share(ShareError::BelowThreshold(3, 2))
+        );
+    }
+
+    #[test]
+    fn test_partial_decryption_proof() {
+        let pp = paramgen::<G>();
+        let (sk, pk) = keygen::<G>(pp);
+        let shares = share_sk::<G>(sk, 2, 3).unwrap();
+        let vks = shares
+            .iter()
+            .map(|share| verification_key::<G>(pp, *share))
+            .collect::<Vec<_>>();
+        let ct = encrypt::<G>(pp, pk, F::from(PTXT));
+
+        let (pdec, proof) = partial_decrypt_with_proof::<G>(pp, shares[0], ct);
+        assert_eq!(pdec, partial_decrypt::<G>(pp, shares[0], ct));
+        assert!(verify_partial_decryption::<G>(pp, vks[0], ct, pdec, &proof));
+        // Wrong verification key, ciphertext or partial decryption
+        assert!(!verify_partial_decryption::<G>(
+            pp, vks[1], ct, pdec, &proof
+        ));
+        let other = encrypt::<G>(pp, pk, F::from(PTXT));
+        assert!(!verify_partial_decryption::<G>(
+            pp, vks[0], other, pdec, &proof
+        ));
+        let forged = (pdec.0, pdec.1 + pp);
+        assert!(!verify_partial_decryption::<G>(
+            pp, vks[0], ct, forged, &proof
+        ));
+    }
+
+    #[test]
+    fn test_bit_proof() {
+        let pp = paramgen::<G>();
+
+        let (sk, pk) = keygen::<G>(pp);
+        for bit in [false, true] {
+            let (ct, proof) = encrypt_bit_with_proof::<G>(pp, pk, bit, b"ctx");
+            assert!(verify_bit::<G>(pp, pk, &ct, &proof, b"ctx"));
+            assert!(!verify_bit::<G>(pp, pk, &ct, &proof, b"other"));
+            assert_eq!(
+                decrypt::<G>(pp, sk, ct, BOUND).unwrap(),
+                F::from(bit as u64)
+            );
+        }
+        let (ct, proof) = encrypt_bit_with_proof::<G>(pp, pk, true, b"ctx");
+        let ct2 = add::<G>(ct, ct);
+        assert!(!verify_bit::<G>(pp, pk, &ct2, &proof, b"ctx"));
+    }
+
+    #[test]
+    fn test_helper_output() {
+        let pp = paramgen::<G>();
+
+        let (sk, pk) = keygen::<G>(pp);
+        let inputs = (0..4)
+            .map(|i| encrypt::<G>(pp, pk, F::from(i as u64)))
             .collect::<Vec<_>>();
-        let inter = intermediate_dec::<P>(pp, pdecs.as_slice(), (1 << 4));
+        let output = evaluate_helper::<G>(3, &inputs);
+        assert!(verify_helper_output::<G>(&inputs, &output));
+        assert_eq!(
+            decrypt::<G>(pp, sk, output.output, BOUND).unwrap(),
+            F::from(6u64)
+        );
 
-        let pdecs2 = sks
+        // Dropped input, or an output that is not the sum
+        assert!(!verify_helper_output::<G>(&inputs[1..], &output));
+        let mut other = output;
+        other.output = add::<G>(output.output, inputs[1]);
+        assert!(!verify_helper_output::<G>(&inputs, &other));
+        let mut other = output;
+        other.period = 4;
+        assert!(!verify_helper_output::<G>(&inputs, &other));
+    }
+
+    #[test]
+    fn test_share_subsets() {
+        const T: usize = 3;
+        const N: usize = 6;
+        let pp = paramgen::<G>();
+        let table = dlog_table::<G>(pp, BOUND);
+
+        let (sk, pk) = keygen::<G>(pp);
+        let sks = share_sk::<G>(sk, T, N).unwrap();
+        let msg = F::from(PTXT);
+        let ct = encrypt::<G>(pp, pk, msg);
+        let pdecs = sks
             .iter()
-            .map(|sk| partial_decrypt2::<P>(pp, *sk, inter))
+            .map(|sk| partial_decrypt::<G>(pp, *sk, ct))
             .collect::<Vec<_>>();
 
-        let pt = final_decrypt::<P>(pp, &pdecs2, (1 << 4));
-        assert_eq!(msg * msg, pt);
+        for subset in 1u32..(1 << N) {
+            let pdecs = (0..N)
+                .filter(|i| subset & (1 << i) != 0)
+                .map(|i| pdecs[i])
+                .collect::<Vec<_>>();
+            let res = final_decrypt_with_table::<G>(&table, ct, &pdecs, T);
+            if pdecs.len() >= T {
+                assert_eq!(res.unwrap(), msg);
+            } else {
+                assert_eq!(
+                    res.unwrap_err(),
+                    ThelgamalError::Share(ShareError::BelowThreshold(T, pdecs.len()))
+                );
+                // Interpolating fewer than T shares does not recover the secret
+                let c1s = reconstruct_secret_in_exp(&pdecs).unwrap();
+                assert_ne!(c1s, ct.0.0 * sk);
+            }
+        }
     }
 }
@@ -0,0 +1,271 @@
+//! Feldman VSS on top of `secret_sharing_and_dkg::feldman_vss`, whose commitments are affine
+//! points. `Commitments` keeps them in projective form, for the arithmetic of the DKG, refresh
+//! and resharing.
+use ark_ec::{AffineRepr, CurveGroup};
+use rand::thread_rng;
+use secret_sharing_and_dkg::common::{CommitmentToCoefficients, Share, ShareId};
+use secret_sharing_and_dkg::error::SSError;
+use secret_sharing_and_dkg::feldman_vss;
+
+/// Errors on the share ids or threshold of a sharing, common to the threshold schemes
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ShareError {
+    /// (threshold, number of shares provided)
+    BelowThreshold(usize, usize),
+    DuplicateShareId(ShareId),
+    /// Share ids start at 1, 0 is where the secret lies
+    ZeroShareId,
+    /// (threshold, total) does not describe a valid sharing
+    InvalidThreshold(usize, usize),
+}
+
+impl std::fmt::Display for ShareError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShareError::BelowThreshold(threshold, len) => {
+                write!(f, "{} shares provided, {} needed", len, threshold)
+            }
+            ShareError::DuplicateShareId(id) => write!(f, "duplicate share id {}", id),
+            ShareError::ZeroShareId => write!(f, "share id 0"),
+            ShareError::InvalidThreshold(threshold, total) => {
+                write!(f, "invalid threshold {} for {} shares", threshold, total)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ShareError {}
+
+/// Check that `ids` contains at least `threshold` distinct, non-zero share ids.
+pub fn check_share_ids(
+    ids: impl Iterator<Item = ShareId>,
+    threshold: usize,
+) -> Result<(), ShareError> {
+    let mut seen = std::collections::HashSet::new();
+    for id in ids {
+        if id == 0 {
+            return Err(ShareError::ZeroShareId);
+        }
+        if !seen.insert(id) {
+            return Err(ShareError::DuplicateShareId(id));
+        }
+    }
+    if threshold == 0 || seen.len() < threshold {
+        return Err(ShareError::BelowThreshold(threshold, seen.len()));
+    }
+    Ok(())
+}
+
+/// Feldman commitments (a_0 G, ..., a_{t-1} G) to the coefficients of a sharing polynomial
+/// f(X) = a_0 + a_1 X + ... + a_{t-1} X^{t-1}.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Commitments<G: CurveGroup>(pub Vec<G>);
+
+impl<G: CurveGroup> Commitments<G> {
+    /// Commitments dealt by `feldman_vss`, padded to `threshold` coefficients: the polynomial
+    /// drops leading zero coefficients, which would hide the threshold
+    fn from_coefficients(coeffs: CommitmentToCoefficients<G::Affine>, threshold: usize) -> Self {
+        let mut commitments = Self::from(&coeffs);
+        commitments.0.resize(threshold, G::zero());
+        commitments
+    }
+
+    /// The same commitments in the affine form used by `secret_sharing_and_dkg`
+    pub fn coefficients(&self) -> CommitmentToCoefficients<G::Affine> {
+        G::normalize_batch(&self.0).into()
+    }
+
+    /// Number of shares needed to reconstruct the committed secret
+    pub fn threshold(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Commitment f(0) G to the secret
+    pub fn public_value(&self) -> G {
+        self.0.first().copied().unwrap_or_else(G::zero)
+    }
+
+    /// f(id) G, computed from the commitments only
+    pub fn eval(&self, id: ShareId) -> G {
+        let x = G::ScalarField::from(id as u64);
+        self.0.iter().rev().fold(G::zero(), |acc, c| acc * x + c)
+    }
+
+    /// Check that `share` is f(id) for the committed polynomial f
+    pub fn verify(&self, base: G, id: ShareId, share: &G::ScalarField) -> bool {
+        let share = Share {
+            id,
+            threshold: self.threshold() as ShareId,
+            share: *share,
+        };
+        id != 0
+            && share
+                .verify(&self.coefficients(), &base.into_affine())
+                .is_ok()
+    }
+
+    /// Commitments to k times the committed polynomial
+    pub fn scale(&self, k: G::ScalarField) -> Self {
+        Commitments(self.0.iter().map(|c| *c * k).collect())
+    }
+
+    /// Commitments to the sum of the committed polynomials
+    pub fn combine<'a>(commitments: impl IntoIterator<Item = &'a Self>) -> Self
+    where
+        G: 'a,
+    {
+        let mut acc: Vec<G> = Vec::new();
+        for c in commitments {
+            if acc.len() < c.0.len() {
+                acc.resize(c.0.len(), G::zero());
+            }
+            for (a, c) in acc.iter_mut().zip(c.0.iter()) {
+                *a += c;
+            }
+        }
+        Commitments(acc)
+    }
+}
+
+impl<G: CurveGroup> From<&CommitmentToCoefficients<G::Affine>> for Commitments<G> {
+    fn from(coeffs: &CommitmentToCoefficients<G::Affine>) -> Self {
+        Commitments(coeffs.0.iter().map(|c| c.into_group()).collect())
+    }
+}
+
+/// Shamir-share `secret` with a random degree-(threshold-1) polynomial and commit to it in base
+/// `base`.
+pub fn deal<G: CurveGroup>(
+    base: G,
+    secret: G::ScalarField,
+    threshold: ShareId,
+    total: ShareId,
+) -> Result<(Vec<(ShareId, G::ScalarField)>, Commitments<G>), SSError> {
+    let mut rng = thread_rng();
+    let (shares, coeffs, _poly) =
+        feldman_vss::deal_secret(&mut rng, secret, threshold, total, &base.into_affine())?;
+    Ok((
+        shares.0.iter().map(|s| (s.id, s.share)).collect(),
+        Commitments::from_coefficients(coeffs, threshold as usize),
+    ))
+}
+
+/// Same as `deal`, with shares for arbitrary non-zero, distinct `ids` instead of 1, ..., total,
+/// which `feldman_vss` does not support: the polynomial is dealt by `feldman_vss` and evaluated
+/// at `ids` here.
+pub fn deal_to<G: CurveGroup>(
+    base: G,
+    secret: G::ScalarField,
+    threshold: usize,
+    ids: &[ShareId],
+) -> Result<(Vec<(ShareId, G::ScalarField)>, Commitments<G>), ShareError> {
+    if threshold == 0 || threshold > ids.len() || ids.len() > ShareId::MAX as usize {
+        return Err(ShareError::InvalidThreshold(threshold, ids.len()));
+    }
+    check_share_ids(ids.iter().copied(), threshold)?;
+    let mut rng = thread_rng();
+    let (_, coeffs, poly) = feldman_vss::deal_secret(
+        &mut rng,
+        secret,
+        threshold as ShareId,
+        threshold as ShareId,
+        &base.into_affine(),
+    )
+    .map_err(|_| ShareError::InvalidThreshold(threshold, ids.len()))?;
+    let shares = ids
+        .iter()
+        .map(|id| {
+            let x = G::ScalarField::from(*id as u64);
+            let share = poly
+                .coeffs
+                .iter()
+                .rev()
+                .fold(G::ScalarField::from(0u64), |acc, a| acc * x + a);
+            (*id, share)
+        })
+        .collect();
+    Ok((shares, Commitments::from_coefficients(coeffs, threshold)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ark_ec::pairing::Pairing;
+    use ark_std::UniformRand;
+
+    type P = ark_bls12_381::Bls12_381;
+    type G2 = <P as Pairing>::G2;
+    type F = <P as Pairing>::ScalarField;
+
+    #[test]
+    fn test_feldman() {
+        let mut rng = thread_rng();
+        let base = G2::rand(&mut rng);
+        let secret = F::rand(&mut rng);
+        let (shares, commitments) = deal(base, secret, 3, 5).unwrap();
+        assert_eq!(commitments.threshold(), 3);
+        assert_eq!(commitments.public_value(), base * secret);
+        for (id, share) in shares.iter() {
+            assert!(commitments.verify(base, *id, share));
+            assert!(!commitments.verify(base, *id, &(*share + F::from(1u64))));
+            assert!(!commitments.verify(base, *id % 5 + 1, share));
+        }
+
+        let (shares2, commitments2) = deal(base, secret, 3, 5).unwrap();
+        let sum = Commitments::combine([&commitments, &commitments2]);
+        assert_eq!(sum.public_value(), base * (secret + secret));
+        for ((id, s1), (_, s2)) in shares.iter().zip(shares2.iter()) {
+            assert!(sum.verify(base, *id, &(*s1 + s2)));
+        }
+    }
+
+    #[test]
+    fn test_check_share_ids() {
+        assert_eq!(check_share_ids([3, 1, 2].into_iter(), 3), Ok(()));
+        assert_eq!(
+            check_share_ids([3, 1].into_iter(), 3),
+            Err(ShareError::BelowThreshold(3, 2))
+        );
+        assert_eq!(
+            check_share_ids([3, 1, 3].into_iter(), 2),
+            Err(ShareError::DuplicateShareId(3))
+        );
+        assert_eq!(
+            check_share_ids([1, 0].into_iter(), 1),
+            Err(ShareError::ZeroShareId)
+        );
+        assert_eq!(
+            check_share_ids(std::iter::empty(), 0),
+            Err(ShareError::BelowThreshold(0, 0))
+        );
+    }
+
+    #[test]
+    fn test_deal_to() {
+        let mut rng = thread_rng();
+        let base = G2::rand(&mut rng);
+        let secret = F::rand(&mut rng);
+        let (shares, commitments) = deal_to(base, secret, 2, &[7, 3, 9]).unwrap();
+        assert_eq!(commitments.public_value(), base * secret);
+        assert_eq!(
+            shares.iter().map(|(id, _)| *id).collect::<Vec<_>>(),
+            [7, 3, 9]
+        );
+        for (id, share) in shares.iter() {
+            assert!(commitments.verify(base, *id, share));
+        }
+
+        assert_eq!(
+            deal_to(base, secret, 2, &[7, 3, 7]).unwrap_err(),
+            ShareError::DuplicateShareId(7)
+        );
+        assert_eq!(
+            deal_to(base, secret, 2, &[0, 3]).unwrap_err(),
+            ShareError::ZeroShareId
+        );
+        assert_eq!(
+            deal_to(base, secret, 3, &[1, 2]).unwrap_err(),
+            ShareError::InvalidThreshold(3, 2)
+        );
+    }
+}